@@ -0,0 +1,81 @@
+//! Reference implementation for sealing and opening envelopes.
+
+use alloc::vec::Vec;
+use chacha20poly1305::{
+	aead::{AeadInPlace, NewAead},
+	Key, Tag, XChaCha20Poly1305, XNonce,
+};
+
+use crate::{EncryptedEnvelope, ALGORITHM_NONE, ALGORITHM_XCHACHA20_POLY1305, NONCE_LEN};
+
+/// Reasons an envelope can't be opened.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+	/// Envelope was sealed with an algorithm this version doesn't know
+	UnsupportedAlgorithm,
+	/// Envelope was sealed with another key or was tampered with
+	InvalidMac,
+}
+
+/// Seals `plaintext` with XChaCha20-Poly1305.
+///
+/// `nonce` must never be used twice with the same key, so clients should pick it at random.
+pub fn seal(
+	key: &[u8; 32],
+	nonce: [u8; NONCE_LEN],
+	plaintext: &[u8],
+) -> EncryptedEnvelope<Vec<u8>> {
+	seal_with_aad(key, nonce, &[], plaintext)
+}
+
+/// Opens `envelope`, returning the plaintext.
+pub fn open<C: AsRef<[u8]>>(
+	key: &[u8; 32],
+	envelope: &EncryptedEnvelope<C>,
+) -> Result<Vec<u8>, Error> {
+	open_with_aad(key, envelope, &[])
+}
+
+// envelopes carry no associated data, it is only taken here to check against published vectors
+pub(crate) fn seal_with_aad(
+	key: &[u8; 32],
+	nonce: [u8; NONCE_LEN],
+	aad: &[u8],
+	plaintext: &[u8],
+) -> EncryptedEnvelope<Vec<u8>> {
+	let mut ciphertext = plaintext.to_vec();
+	let mac = XChaCha20Poly1305::new(&Key::from(*key))
+		.encrypt_in_place_detached(&XNonce::from(nonce), aad, &mut ciphertext)
+		.expect("contact entries are far below the cipher length limit; qed");
+
+	EncryptedEnvelope {
+		algorithm: ALGORITHM_XCHACHA20_POLY1305,
+		nonce,
+		ciphertext,
+		mac: mac.into(),
+	}
+}
+
+pub(crate) fn open_with_aad<C: AsRef<[u8]>>(
+	key: &[u8; 32],
+	envelope: &EncryptedEnvelope<C>,
+	aad: &[u8],
+) -> Result<Vec<u8>, Error> {
+	let mut plaintext = envelope.ciphertext.as_ref().to_vec();
+
+	match envelope.algorithm {
+		ALGORITHM_NONE => Ok(plaintext),
+		ALGORITHM_XCHACHA20_POLY1305 => {
+			XChaCha20Poly1305::new(&Key::from(*key))
+				.decrypt_in_place_detached(
+					&XNonce::from(envelope.nonce),
+					aad,
+					&mut plaintext,
+					&Tag::from(envelope.mac),
+				)
+				.map_err(|_| Error::InvalidMac)?;
+			Ok(plaintext)
+		},
+		_ => Err(Error::UnsupportedAlgorithm),
+	}
+}
@@ -0,0 +1,73 @@
+//! Envelope format for encrypted contact entries of the template pallet.
+//!
+//! The pallet only checks that an envelope names a known algorithm, sealing and opening happens
+//! in the clients with a key that never leaves them. The `cipher` feature provides a reference
+//! implementation, so that every client produces envelopes the others can open.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "cipher")]
+extern crate alloc;
+
+#[cfg(feature = "cipher")]
+mod cipher;
+#[cfg(all(test, feature = "cipher"))]
+mod tests;
+
+#[cfg(feature = "cipher")]
+pub use cipher::{open, seal, Error};
+
+use codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+
+/// Content is stored as is. Only used for entries written before envelopes were introduced, new
+/// entries can't use it.
+pub const ALGORITHM_NONE: u8 = 0;
+/// XChaCha20-Poly1305 with a random nonce and no associated data.
+pub const ALGORITHM_XCHACHA20_POLY1305: u8 = 1;
+
+/// Length of the envelope nonce.
+pub const NONCE_LEN: usize = 24;
+/// Length of the envelope MAC.
+pub const MAC_LEN: usize = 16;
+
+/// Encrypted content along with the parameters needed to open it.
+#[derive(Clone, Default, Encode, Decode, Eq, PartialEq, MaxEncodedLen, TypeInfo, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct EncryptedEnvelope<Ciphertext> {
+	pub algorithm: u8,
+	pub nonce: [u8; NONCE_LEN],
+	pub ciphertext: Ciphertext,
+	pub mac: [u8; MAC_LEN],
+}
+
+impl<Ciphertext> EncryptedEnvelope<Ciphertext> {
+	/// Wraps content which is stored as is, for entries written before envelopes were introduced.
+	pub fn plain(content: Ciphertext) -> Self {
+		Self {
+			algorithm: ALGORITHM_NONE,
+			nonce: [0; NONCE_LEN],
+			ciphertext: content,
+			mac: [0; MAC_LEN],
+		}
+	}
+
+	/// Returns whether the envelope was sealed with an algorithm new entries can use, which rules
+	/// out plain envelopes.
+	pub fn is_supported(&self) -> bool {
+		self.algorithm == ALGORITHM_XCHACHA20_POLY1305
+	}
+
+	/// Converts the ciphertext into another container.
+	pub fn map<C>(self, f: impl FnOnce(Ciphertext) -> C) -> EncryptedEnvelope<C> {
+		EncryptedEnvelope {
+			algorithm: self.algorithm,
+			nonce: self.nonce,
+			ciphertext: f(self.ciphertext),
+			mac: self.mac,
+		}
+	}
+}
@@ -0,0 +1,98 @@
+use crate::{
+	cipher::{open_with_aad, seal_with_aad},
+	*,
+};
+use codec::Encode;
+
+fn hex(s: &str) -> Vec<u8> {
+	(0..s.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+		.collect()
+}
+
+// XChaCha20-Poly1305 example from draft-irtf-cfrg-xchacha-03, section A.3.1
+const XCHACHA_KEY: &str = "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f";
+const XCHACHA_NONCE: &str = "404142434445464748494a4b4c4d4e4f5051525354555657";
+const XCHACHA_AAD: &str = "50515253c0c1c2c3c4c5c6c7";
+const XCHACHA_PLAINTEXT: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you \
+	only one tip for the future, sunscreen would be it.";
+const XCHACHA_CIPHERTEXT: &str = concat!(
+	"bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb",
+	"731c7f1b0b4aa6440bf3a82f4eda7e39ae64c6708c54c216cb96b72e1213b452",
+	"2f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff9",
+	"21f9664c97637da9768812f615c68b13b52e",
+);
+const XCHACHA_MAC: &str = "c0875924c1c7987947deafd8780acf49";
+
+#[test]
+fn seal_matches_draft_example() {
+	let envelope = seal_with_aad(
+		&hex(XCHACHA_KEY).try_into().unwrap(),
+		hex(XCHACHA_NONCE).try_into().unwrap(),
+		&hex(XCHACHA_AAD),
+		XCHACHA_PLAINTEXT,
+	);
+
+	assert_eq!(envelope.algorithm, ALGORITHM_XCHACHA20_POLY1305);
+	assert_eq!(envelope.ciphertext, hex(XCHACHA_CIPHERTEXT));
+	assert_eq!(envelope.mac.to_vec(), hex(XCHACHA_MAC));
+}
+
+#[test]
+fn open_matches_draft_example() {
+	let key = hex(XCHACHA_KEY).try_into().unwrap();
+	let envelope = EncryptedEnvelope {
+		algorithm: ALGORITHM_XCHACHA20_POLY1305,
+		nonce: hex(XCHACHA_NONCE).try_into().unwrap(),
+		ciphertext: hex(XCHACHA_CIPHERTEXT),
+		mac: hex(XCHACHA_MAC).try_into().unwrap(),
+	};
+	let aad = hex(XCHACHA_AAD);
+	assert_eq!(open_with_aad(&key, &envelope, &aad), Ok(XCHACHA_PLAINTEXT.to_vec()));
+
+	// the associated data is authenticated too
+	assert_eq!(open(&key, &envelope), Err(Error::InvalidMac));
+
+	let mut tampered = envelope;
+	tampered.mac[0] ^= 1;
+	assert_eq!(open_with_aad(&key, &tampered, &aad), Err(Error::InvalidMac));
+}
+
+#[test]
+fn sealed_envelopes_open_with_the_same_key() {
+	let envelope = seal(&[9; 32], [3; NONCE_LEN], b"Bob");
+
+	assert_eq!(open(&[9; 32], &envelope), Ok(b"Bob".to_vec()));
+	assert_eq!(open(&[8; 32], &envelope), Err(Error::InvalidMac));
+}
+
+#[test]
+fn plain_and_unknown_envelopes() {
+	let key = [0x42; 32];
+
+	// entries written before envelopes can still be read but not written anymore
+	let plain = EncryptedEnvelope::plain(b"alice".to_vec());
+	assert!(!plain.is_supported());
+	assert_eq!(open(&key, &plain), Ok(b"alice".to_vec()));
+
+	let unknown = EncryptedEnvelope { algorithm: 2, ..EncryptedEnvelope::plain(Vec::new()) };
+	assert!(!unknown.is_supported());
+	assert_eq!(open(&key, &unknown), Err(Error::UnsupportedAlgorithm));
+}
+
+#[test]
+fn encoding_layout() {
+	let envelope = seal_with_aad(
+		&hex(XCHACHA_KEY).try_into().unwrap(),
+		hex(XCHACHA_NONCE).try_into().unwrap(),
+		&hex(XCHACHA_AAD),
+		XCHACHA_PLAINTEXT,
+	);
+
+	// algorithm, nonce, ciphertext prefixed with its compact encoded length of 114 and MAC
+	let expected =
+		[hex("01"), hex(XCHACHA_NONCE), hex("c901"), hex(XCHACHA_CIPHERTEXT), hex(XCHACHA_MAC)]
+			.concat();
+	assert_eq!(envelope.encode(), expected);
+}
@@ -0,0 +1,133 @@
+//! Runtime API definition for the template pallet.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use codec::{Codec, Decode, Encode};
+use pallet_template_envelope::EncryptedEnvelope;
+use scale_info::TypeInfo;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::RuntimeDebug;
+use sp_std::vec::Vec;
+
+/// Nickname registration of an account.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Registration {
+	pub nickname: Vec<u8>,
+	pub address: [u8; 32],
+}
+
+/// Contact stored by an account.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Contact<AccountId> {
+	pub addr: Vec<u8>,
+	/// Name sealed by the owner's clients.
+	pub name: EncryptedEnvelope<Vec<u8>>,
+	pub account: Option<AccountId>,
+	/// Ids of the owner's contact groups the contact is in.
+	pub groups: Vec<u32>,
+	pub flags: u32,
+	/// Note sealed by the owner's clients.
+	pub note: Option<EncryptedEnvelope<Vec<u8>>>,
+}
+
+/// Named group an account sorts its contacts into.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct ContactGroup {
+	pub id: u32,
+	/// Name sealed by the owner's clients.
+	pub name: EncryptedEnvelope<Vec<u8>>,
+}
+
+/// Chat offer which is still waiting for an answer.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct PendingOffer<AccountId, BlockNumber> {
+	pub offered_by: AccountId,
+	pub session_id: u64,
+	pub offer: Vec<u8>,
+	pub welcome_msg: Vec<u8>,
+	pub created_at: BlockNumber,
+	/// Room the offer was made in, if any
+	pub room: Option<u64>,
+	/// Device the offer is for, all devices if `None`
+	pub device: Option<u32>,
+}
+
+/// Device an account receives chat offers on.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct Device<BlockNumber> {
+	pub id: u32,
+	pub public_key: [u8; 32],
+	pub label: Vec<u8>,
+	pub added_at: BlockNumber,
+}
+
+/// Chat signal addressed to an account: an offer, an answer, ICE candidates, or the rejection or
+/// cancellation of an offer.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(tag = "type", rename_all = "camelCase"))]
+pub enum Signal<AccountId> {
+	#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+	Offer {
+		offered_by: AccountId,
+		session_id: u64,
+		offer: Vec<u8>,
+		welcome_msg: Vec<u8>,
+		room: Option<u64>,
+		device: Option<u32>,
+	},
+	#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+	Answer { answer_from: AccountId, session_id: u64, answer: Vec<u8> },
+	#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+	IceCandidates { from: AccountId, session_id: u64, candidates: Vec<u8> },
+	#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+	Rejected { offered_to: AccountId, session_id: u64, reason_code: u8 },
+	#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+	Cancelled { offered_by: AccountId, session_id: u64 },
+}
+
+sp_api::decl_runtime_apis! {
+	/// Lookups into the template pallet storage, so that clients don't have to decode raw
+	/// storage keys themselves.
+	pub trait DiffyChatApi<AccountId, BlockNumber>
+	where
+		AccountId: Codec,
+		BlockNumber: Codec,
+	{
+		/// Returns the owner of `nickname`.
+		fn resolve_nickname(nickname: Vec<u8>) -> Option<AccountId>;
+
+		/// Returns the registration of `account`.
+		fn reverse_lookup(account: AccountId) -> Option<Registration>;
+
+		/// Returns the payload a messaging key has to sign to be registered by `account`.
+		fn key_binding_payload(account: AccountId) -> Vec<u8>;
+
+		/// Returns the devices of `account`.
+		fn devices_of(account: AccountId) -> Vec<Device<BlockNumber>>;
+
+		/// Returns one page of the contacts stored by `account`.
+		fn contacts_of(account: AccountId, page: u32) -> Vec<Contact<AccountId>>;
+
+		/// Returns the contact groups of `account`.
+		fn contact_groups_of(account: AccountId) -> Vec<ContactGroup>;
+
+		/// Returns the offers made to `account` which are still pending.
+		fn pending_offers(account: AccountId) -> Vec<PendingOffer<AccountId, BlockNumber>>;
+
+		/// Returns the offers, answers, ICE candidates, rejections and cancellations addressed to
+		/// `account` in the current block.
+		fn signals_of(account: AccountId) -> Vec<Signal<AccountId>>;
+	}
+}
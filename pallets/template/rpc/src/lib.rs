@@ -0,0 +1,244 @@
+//! RPC interface for the template pallet.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use codec::Codec;
+use futures::{future, FutureExt, StreamExt};
+use jsonrpsee::{
+	core::RpcResult,
+	proc_macros::rpc,
+	types::{
+		error::{CallError, ErrorObject},
+		SubscriptionResult,
+	},
+	SubscriptionSink,
+};
+use sc_client_api::BlockchainEvents;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_core::{traits::SpawnNamed, Bytes};
+use sp_runtime::{
+	generic::BlockId,
+	traits::{Block as BlockT, MaybeSerializeDeserialize},
+};
+
+pub use pallet_template_runtime_api::{
+	Contact, ContactGroup, Device, DiffyChatApi as DiffyChatRuntimeApi, PendingOffer, Registration,
+	Signal,
+};
+
+/// Signals addressed to the subscribed account in one block.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignalNotification<BlockHash, AccountId> {
+	pub block: BlockHash,
+	pub signals: Vec<Signal<AccountId>>,
+}
+
+#[rpc(client, server)]
+pub trait DiffyChatApi<BlockHash, AccountId, BlockNumber> {
+	/// Returns the owner of `nickname`.
+	#[method(name = "diffychat_resolveNickname")]
+	fn resolve_nickname(
+		&self,
+		nickname: String,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<AccountId>>;
+
+	/// Returns the nickname registration of `account`.
+	#[method(name = "diffychat_reverseLookup")]
+	fn reverse_lookup(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Option<Registration>>;
+
+	/// Returns the payload a messaging key has to sign to be registered by `account`.
+	#[method(name = "diffychat_keyBindingPayload")]
+	fn key_binding_payload(&self, account: AccountId, at: Option<BlockHash>) -> RpcResult<Bytes>;
+
+	/// Returns the devices of `account`.
+	#[method(name = "diffychat_devicesOf")]
+	fn devices_of(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<Device<BlockNumber>>>;
+
+	/// Returns one page of the contacts stored by `account`.
+	#[method(name = "diffychat_contactsOf")]
+	fn contacts_of(
+		&self,
+		account: AccountId,
+		page: u32,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<Contact<AccountId>>>;
+
+	/// Returns the contact groups of `account`.
+	#[method(name = "diffychat_contactGroupsOf")]
+	fn contact_groups_of(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<ContactGroup>>;
+
+	/// Returns the offers made to `account` which are still pending.
+	#[method(name = "diffychat_pendingOffers")]
+	fn pending_offers(
+		&self,
+		account: AccountId,
+		at: Option<BlockHash>,
+	) -> RpcResult<Vec<PendingOffer<AccountId, BlockNumber>>>;
+
+	/// Pushes the signals addressed to `account` as blocks are imported, or as they are finalized
+	/// if `finalized` is set.
+	#[subscription(
+		name = "diffychat_subscribeSignals" => "diffychat_signals",
+		unsubscribe = "diffychat_unsubscribeSignals",
+		item = SignalNotification<BlockHash, AccountId>,
+	)]
+	fn subscribe_signals(&self, account: AccountId, finalized: Option<bool>);
+}
+
+/// Provides RPC methods to query the template pallet.
+pub struct DiffyChat<C, Block> {
+	client: Arc<C>,
+	executor: Arc<dyn SpawnNamed>,
+	_marker: PhantomData<Block>,
+}
+
+impl<C, Block> DiffyChat<C, Block> {
+	/// Creates a new instance of the DiffyChat RPC handler.
+	///
+	/// Subscriptions are driven by tasks spawned on `executor`.
+	pub fn new(client: Arc<C>, executor: Arc<dyn SpawnNamed>) -> Self {
+		Self { client, executor, _marker: Default::default() }
+	}
+}
+
+/// Error code of a failed runtime API call.
+const RUNTIME_ERROR: i32 = 1;
+
+fn runtime_error(err: impl std::fmt::Debug) -> jsonrpsee::core::Error {
+	CallError::Custom(ErrorObject::owned(
+		RUNTIME_ERROR,
+		"Unable to query the DiffyChat runtime API",
+		Some(format!("{:?}", err)),
+	))
+	.into()
+}
+
+impl<C, Block, AccountId, BlockNumber>
+	DiffyChatApiServer<<Block as BlockT>::Hash, AccountId, BlockNumber> for DiffyChat<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block>
+		+ HeaderBackend<Block>
+		+ BlockchainEvents<Block>
+		+ Send
+		+ Sync
+		+ 'static,
+	C::Api: DiffyChatRuntimeApi<Block, AccountId, BlockNumber>,
+	AccountId: Codec + Clone + MaybeSerializeDeserialize + Send + Sync + 'static,
+	BlockNumber: Codec + MaybeSerializeDeserialize + Send + Sync + 'static,
+{
+	fn resolve_nickname(
+		&self,
+		nickname: String,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<AccountId>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client
+			.runtime_api()
+			.resolve_nickname(&at, nickname.into_bytes())
+			.map_err(runtime_error)
+	}
+
+	fn reverse_lookup(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Option<Registration>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().reverse_lookup(&at, account).map_err(runtime_error)
+	}
+
+	fn key_binding_payload(&self, account: AccountId, at: Option<Block::Hash>) -> RpcResult<Bytes> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client
+			.runtime_api()
+			.key_binding_payload(&at, account)
+			.map(Into::into)
+			.map_err(runtime_error)
+	}
+
+	fn devices_of(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<Device<BlockNumber>>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().devices_of(&at, account).map_err(runtime_error)
+	}
+
+	fn contacts_of(
+		&self,
+		account: AccountId,
+		page: u32,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<Contact<AccountId>>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().contacts_of(&at, account, page).map_err(runtime_error)
+	}
+
+	fn contact_groups_of(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<ContactGroup>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().contact_groups_of(&at, account).map_err(runtime_error)
+	}
+
+	fn pending_offers(
+		&self,
+		account: AccountId,
+		at: Option<Block::Hash>,
+	) -> RpcResult<Vec<PendingOffer<AccountId, BlockNumber>>> {
+		let at = BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		self.client.runtime_api().pending_offers(&at, account).map_err(runtime_error)
+	}
+
+	fn subscribe_signals(
+		&self,
+		mut sink: SubscriptionSink,
+		account: AccountId,
+		finalized: Option<bool>,
+	) -> SubscriptionResult {
+		let blocks = if finalized.unwrap_or(false) {
+			self.client.finality_notification_stream().map(|block| block.hash).boxed()
+		} else {
+			self.client.import_notification_stream().map(|block| block.hash).boxed()
+		};
+
+		let client = self.client.clone();
+		let notifications = blocks.filter_map(move |hash| {
+			// blocks without signals for the account, or whose state is already pruned, are skipped
+			let signals = client
+				.runtime_api()
+				.signals_of(&BlockId::hash(hash), account.clone())
+				.ok()
+				.filter(|signals| !signals.is_empty())
+				.map(|signals| SignalNotification { block: hash, signals });
+			future::ready(signals)
+		});
+
+		let fut = async move {
+			sink.pipe_from_stream(notifications).await;
+		};
+		self.executor.spawn("diffychat-rpc-subscription", Some("rpc"), fut.boxed());
+
+		Ok(())
+	}
+}
@@ -4,16 +4,813 @@ use super::*;
 
 #[allow(unused)]
 use crate::Pallet as Template;
-use frame_benchmarking::{benchmarks, whitelisted_caller};
+use frame_benchmarking::{account, benchmarks, whitelisted_caller, BenchmarkError};
+use frame_support::{
+	sp_io,
+	sp_runtime::{
+		app_crypto::ed25519,
+		traits::{Bounded, Saturating, Zero},
+		KeyTypeId,
+	},
+	sp_std::{collections::btree_map::BTreeMap, vec, vec::Vec},
+	traits::{Currency, EnsureOrigin, Get, Hooks},
+	weights::Weight,
+	BoundedVec,
+};
 use frame_system::RawOrigin;
 
+const SEED: u32 = 0;
+
+fn funded<T: Config>(who: T::AccountId) -> T::AccountId {
+	T::Currency::make_free_balance_be(&who, BalanceOf::<T>::max_value() / 2_u32.into());
+	who
+}
+
+fn bytes<S: Get<u32>>(byte: u8, len: u32) -> BoundedVec<u8, S> {
+	vec![byte; len as usize].try_into().expect("length is within the bound; qed")
+}
+
+// envelope with made up nonce and MAC, the pallet doesn't open them
+fn envelope<S: Get<u32>>(byte: u8, len: u32) -> EncryptedEnvelope<BoundedVec<u8, S>> {
+	EncryptedEnvelope {
+		algorithm: pallet_template_envelope::ALGORITHM_XCHACHA20_POLY1305,
+		nonce: [byte; pallet_template_envelope::NONCE_LEN],
+		ciphertext: bytes(byte, len),
+		mac: [byte; pallet_template_envelope::MAC_LEN],
+	}
+}
+
+// distinct contact addresses of the maximum length
+fn contact_addrs<T: Config>(count: u32) -> Vec<EncodedContactAddr<T>> {
+	(0..count)
+		.map(|i| {
+			let mut addr = vec![1; T::MaxContactAddrLen::get() as usize];
+			addr[..4].copy_from_slice(&i.to_le_bytes());
+			addr.try_into().expect("length is within the bound; qed")
+		})
+		.collect()
+}
+
+// inserts `count` contacts of the maximum size, each linked to an account
+fn linked_contacts<T: Config>(
+	who: &T::AccountId,
+	count: u32,
+) -> Result<Vec<EncodedContactAddr<T>>, BenchmarkError> {
+	let contact_addrs = contact_addrs::<T>(count);
+	for (i, contact_addr) in contact_addrs.iter().enumerate() {
+		Template::<T>::upsert_contact(
+			RawOrigin::Signed(who.clone()).into(),
+			envelope(1, T::MaxContactNameLen::get()),
+			contact_addr.clone(),
+		)?;
+		Template::<T>::set_contact_account(
+			RawOrigin::Signed(who.clone()).into(),
+			contact_addr.clone(),
+			Some(account("contact", i as u32, SEED)),
+		)?;
+	}
+	Ok(contact_addrs)
+}
+
+// creates `count` contact groups with names of the maximum length
+fn contact_groups<T: Config>(who: &T::AccountId, count: u32) -> Result<(), BenchmarkError> {
+	for _ in 0..count {
+		Template::<T>::create_contact_group(
+			RawOrigin::Signed(who.clone()).into(),
+			envelope(1, T::MaxContactGroupNameLen::get()),
+		)?;
+	}
+	Ok(())
+}
+
+const KEY_TYPE: KeyTypeId = KeyTypeId(*b"dfyc");
+
+// generates a messaging key along with the proof binding it to `who`
+fn messaging_key<T: Config>(who: &T::AccountId) -> ([u8; 32], [u8; 64]) {
+	let public = sp_io::crypto::ed25519_generate(KEY_TYPE, None);
+	let key_proof =
+		sp_io::crypto::ed25519_sign(KEY_TYPE, &public, &Template::<T>::key_binding_payload(who))
+			.expect("key was just generated; qed");
+	(public.0, key_proof.0)
+}
+
+fn register_max<T: Config>(who: &T::AccountId, byte: u8) -> Result<(), BenchmarkError> {
+	let (address, key_proof) = messaging_key::<T>(who);
+	Template::<T>::register(
+		RawOrigin::Signed(who.clone()).into(),
+		bytes(byte, T::MaxNicknameLen::get()),
+		address,
+		key_proof,
+	)?;
+	// a full prekey bundle has to be released along with the registration
+	Template::<T>::publish_prekeys(
+		RawOrigin::Signed(who.clone()).into(),
+		signed_prekey(address),
+		prekeys::<T>(T::MaxOneTimePrekeys::get()),
+	)?;
+	// and so do all its devices
+	for _ in 0..T::MaxDevices::get() {
+		let (public_key, key_proof) = messaging_key::<T>(who);
+		Template::<T>::add_device(
+			RawOrigin::Signed(who.clone()).into(),
+			public_key,
+			bytes(1, T::MaxDeviceLabelLen::get()),
+			key_proof,
+		)?;
+	}
+	Ok(())
+}
+
+// a prekey signed by the messaging key published as `address`
+fn signed_prekey(address: [u8; 32]) -> SignedPrekey {
+	let key = [2_u8; 32];
+	let signature =
+		sp_io::crypto::ed25519_sign(KEY_TYPE, &ed25519::Public::from_raw(address), &key)
+			.expect("address is a key of the keystore; qed");
+	SignedPrekey { key, signature: signature.0 }
+}
+
+fn prekeys<T: Config>(count: u32) -> OneTimePrekeys<T> {
+	(0..count)
+		.map(|i| [i as u8; 32])
+		.collect::<Vec<_>>()
+		.try_into()
+		.expect("count is within the bound; qed")
+}
+
+// creates a room administered by `admin` with `members` members in total
+fn room_with_members<T: Config>(
+	admin: &T::AccountId,
+	members: u32,
+) -> Result<RoomId, BenchmarkError> {
+	let room_id = NextRoomId::<T>::get();
+	Template::<T>::create_room(
+		RawOrigin::Signed(admin.clone()).into(),
+		bytes(b'a', T::MaxRoomNameLen::get()),
+	)?;
+	for i in 1..members {
+		let member = funded::<T>(account("member", i, SEED));
+		Template::<T>::invite(RawOrigin::Signed(admin.clone()).into(), room_id, member.clone())?;
+		Template::<T>::join(RawOrigin::Signed(member).into(), room_id)?;
+	}
+	Ok(room_id)
+}
+
+// creates a room of `admin` with the maximum number of pending invites
+fn room_with_invites<T: Config>(admin: &T::AccountId) -> Result<RoomId, BenchmarkError> {
+	let room_id = room_with_members::<T>(admin, 1)?;
+	for i in 0..T::MaxRoomMembers::get() {
+		Template::<T>::invite(
+			RawOrigin::Signed(admin.clone()).into(),
+			room_id,
+			account("invitee", i, SEED),
+		)?;
+	}
+	Ok(room_id)
+}
+
+// a full size copy of the room key for each member
+fn room_key_copies<T: Config>(room_id: RoomId) -> RoomKeyCopies<T> {
+	let copies: BTreeMap<_, _> = Rooms::<T>::get(room_id)
+		.map(|room| room.members.into_inner())
+		.unwrap_or_default()
+		.into_iter()
+		.map(|member| (member.account, bytes(1, T::MaxRoomKeyLen::get())))
+		.collect();
+	copies.try_into().expect("one copy per member; qed")
+}
+
+// posts the room key of the current epoch, which a membership change has to release
+fn post_room_key<T: Config>(admin: &T::AccountId, room_id: RoomId) -> Result<(), BenchmarkError> {
+	Template::<T>::set_room_key(
+		RawOrigin::Signed(admin.clone()).into(),
+		room_id,
+		RoomKeyEpochs::<T>::get(room_id),
+		room_key_copies::<T>(room_id),
+	)?;
+	Ok(())
+}
+
+// makes `count` full size offers from distinct accounts, returns the block they come due at
+fn offers_due<T: Config>(count: u32) -> Result<T::BlockNumber, BenchmarkError> {
+	let to: T::AccountId = account("offeree", 0, SEED);
+	for i in 0..count {
+		Template::<T>::offer_chat(
+			RawOrigin::Signed(account("offerer", i, SEED)).into(),
+			bytes(1, T::MaxWelcomeMsgLen::get()),
+			bytes(1, T::MaxOfferLen::get()),
+			to.clone(),
+			None,
+			None,
+		)?;
+	}
+	Ok(frame_system::Pallet::<T>::block_number().saturating_add(T::OfferTtl::get()))
+}
+
+fn assert_last_event<T: Config>(event: Event<T>) {
+	frame_system::Pallet::<T>::assert_last_event(<T as Config>::RuntimeEvent::from(event).into());
+}
+
 benchmarks! {
-	do_something {
-		let s in 0 .. 100;
+	offer_chat {
+		let o in 0 .. T::MaxOfferLen::get();
+		let w in 0 .. T::MaxWelcomeMsgLen::get();
+
+		let caller: T::AccountId = whitelisted_caller();
+		let to = funded::<T>(account("offeree", 0, SEED));
+		let offer: OfferPayload<T> = bytes(1, o);
+		let welcome_msg: WelcomeMsg<T> = bytes(1, w);
+
+		// the targeted device is the last one of the offeree
+		register_max::<T>(&to, b'o')?;
+		let device = T::MaxDevices::get().checked_sub(1);
+
+		let session_id = NextSessionId::<T>::get();
+	}: _(RawOrigin::Signed(caller.clone()), welcome_msg, offer, to.clone(), None, device)
+	verify {
+		assert!(ChatSessions::<T>::contains_key((&caller, &to, session_id)));
+	}
+
+	answer_chat {
+		let a in 0 .. T::MaxAnswerLen::get();
+
+		let caller: T::AccountId = whitelisted_caller();
+		let offerer: T::AccountId = account("offerer", 0, SEED);
+		let session_id = NextSessionId::<T>::get();
+		Template::<T>::offer_chat(
+			RawOrigin::Signed(offerer.clone()).into(),
+			bytes(1, T::MaxWelcomeMsgLen::get()),
+			bytes(1, T::MaxOfferLen::get()),
+			caller.clone(),
+			None, None,
+		)?;
+		let answer: AnswerPayload<T> = bytes(1, a);
+	}: _(RawOrigin::Signed(caller.clone()), answer, offerer.clone(), session_id)
+	verify {
+		assert_eq!(
+			ChatSessions::<T>::get((&offerer, &caller, session_id)).map(|session| session.state),
+			Some(SessionState::Answered)
+		);
+	}
+
+	register {
+		let n in 3 .. T::MaxNicknameLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let nickname: Nickname<T> = bytes(b'a', n);
+		let (address, key_proof) = messaging_key::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), nickname.clone(), address, key_proof)
+	verify {
+		assert_eq!(ItemByNicknameStore::<T>::get(&nickname), Some(caller));
+	}
+
+	upsert_contact {
+		let n in 0 .. T::MaxContactNameLen::get();
+		let a in 0 .. T::MaxContactAddrLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let contact_name: ContactEnvelope<T> = envelope(1, n);
+		// a new contact also has to be counted
+		let contact_addr: EncodedContactAddr<T> = bytes(1, a);
+	}: _(RawOrigin::Signed(caller.clone()), contact_name.clone(), contact_addr.clone())
+	verify {
+		assert_eq!(ContactByAccountIdStore::<T>::get(&caller, &contact_addr).name, contact_name);
+		assert_eq!(ContactCount::<T>::get(&caller), 1);
+	}
+
+	remove_contact {
+		let a in 0 .. T::MaxContactAddrLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let contact_addr: EncodedContactAddr<T> = bytes(1, a);
+		Template::<T>::upsert_contact(
+			RawOrigin::Signed(caller.clone()).into(),
+			envelope(1, T::MaxContactNameLen::get()),
+			contact_addr.clone(),
+		)?;
+
+		// a linked contact also has to be removed from the index
+		let account: T::AccountId = account("contact", 0, SEED);
+		Template::<T>::set_contact_account(
+			RawOrigin::Signed(caller.clone()).into(),
+			contact_addr.clone(),
+			Some(account.clone()),
+		)?;
+	}: _(RawOrigin::Signed(caller.clone()), contact_addr.clone())
+	verify {
+		assert!(!ContactByAccountIdStore::<T>::contains_key(&caller, &contact_addr));
+		assert!(!ContactIndex::<T>::contains_key(&caller, &account));
+	}
+
+	batch_upsert_contacts {
+		let c in 0 .. T::MaxContactBatch::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let contacts: ContactBatch<T> = contact_addrs::<T>(c)
+			.into_iter()
+			.map(|contact_addr| {
+				(envelope(1, T::MaxContactNameLen::get()), contact_addr)
+			})
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("batch is within the bound; qed");
+	}: _(RawOrigin::Signed(caller.clone()), contacts.clone())
+	verify {
+		for (contact_name, contact_addr) in contacts {
+			assert_eq!(ContactByAccountIdStore::<T>::get(&caller, &contact_addr).name, contact_name);
+		}
+	}
+
+	batch_remove_contacts {
+		let c in 0 .. T::MaxContactBatch::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let contact_addrs: ContactAddrBatch<T> = linked_contacts::<T>(&caller, c)?
+			.try_into()
+			.expect("batch is within the bound; qed");
+	}: _(RawOrigin::Signed(caller.clone()), contact_addrs)
+	verify {
+		assert_eq!(ContactCount::<T>::get(&caller), 0);
+		assert_eq!(ContactByAccountIdStore::<T>::iter_prefix(&caller).count(), 0);
+		assert_eq!(ContactIndex::<T>::iter_prefix(&caller).count(), 0);
+	}
+
+	clear_contacts {
+		let c in 0 .. T::MaxContactBatch::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		linked_contacts::<T>(&caller, c)?;
+	}: _(RawOrigin::Signed(caller.clone()), c)
+	verify {
+		assert_eq!(ContactCount::<T>::get(&caller), 0);
+		assert_eq!(ContactByAccountIdStore::<T>::iter_prefix(&caller).count(), 0);
+		assert_eq!(ContactIndex::<T>::iter_prefix(&caller).count(), 0);
+	}
+
+	create_contact_group {
+		let caller = funded::<T>(whitelisted_caller());
+		contact_groups::<T>(&caller, T::MaxContactGroups::get() - 1)?;
+		let name: ContactGroupName<T> =
+			envelope(1, T::MaxContactGroupNameLen::get());
+	}: _(RawOrigin::Signed(caller.clone()), name)
+	verify {
+		assert_eq!(
+			ContactGroupsOf::<T>::get(&caller).groups.len() as u32,
+			T::MaxContactGroups::get()
+		);
+	}
+
+	rename_contact_group {
+		let caller = funded::<T>(whitelisted_caller());
+		contact_groups::<T>(&caller, T::MaxContactGroups::get())?;
+		let name: ContactGroupName<T> =
+			envelope(2, T::MaxContactGroupNameLen::get());
+	}: _(RawOrigin::Signed(caller.clone()), 0, name.clone())
+	verify {
+		assert_eq!(ContactGroupsOf::<T>::get(&caller).groups.get(&0), Some(&name));
+	}
+
+	remove_contact_group {
+		let caller = funded::<T>(whitelisted_caller());
+		contact_groups::<T>(&caller, T::MaxContactGroups::get())?;
+	}: _(RawOrigin::Signed(caller.clone()), 0)
+	verify {
+		assert!(!ContactGroupsOf::<T>::get(&caller).groups.contains_key(&0));
+	}
+
+	set_contact_metadata {
+		let a in 0 .. T::MaxContactAddrLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let contact_addr: EncodedContactAddr<T> = bytes(1, a);
+		Template::<T>::upsert_contact(
+			RawOrigin::Signed(caller.clone()).into(),
+			envelope(1, T::MaxContactNameLen::get()),
+			contact_addr.clone(),
+		)?;
+		contact_groups::<T>(&caller, T::MaxContactGroups::get())?;
+		let groups: BoundedVec<GroupId, T::MaxContactGroups> = (0..T::MaxContactGroups::get())
+			.collect::<Vec<_>>()
+			.try_into()
+			.expect("length is within the bound; qed");
+		let note: ContactNote<T> = envelope(1, T::MaxContactNoteLen::get());
+	}: _(
+		RawOrigin::Signed(caller.clone()),
+		contact_addr.clone(),
+		groups.clone(),
+		CONTACT_FAVOURITE,
+		Some(note)
+	)
+	verify {
+		assert_eq!(ContactByAccountIdStore::<T>::get(&caller, &contact_addr).groups, groups);
+	}
+
+	unregister {
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'a')?;
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert!(!ItemByAccountIdStore::<T>::contains_key(&caller));
+		assert!(!Devices::<T>::contains_key(&caller));
+	}
+
+	add_device {
+		let l in 0 .. T::MaxDeviceLabelLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'a')?;
+		// the new key has to be compared with all other devices
+		Template::<T>::remove_device(RawOrigin::Signed(caller.clone()).into(), 0)?;
+
+		let (public_key, key_proof) = messaging_key::<T>(&caller);
+		let label: DeviceLabel<T> = bytes(1, l);
+	}: _(RawOrigin::Signed(caller.clone()), public_key, label, key_proof)
+	verify {
+		assert_eq!(Devices::<T>::get(&caller).len() as u32, T::MaxDevices::get());
+	}
+
+	remove_device {
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'a')?;
+		let device_id = T::MaxDevices::get() - 1;
+	}: _(RawOrigin::Signed(caller.clone()), device_id)
+	verify {
+		assert_last_event::<T>(Event::DeviceRemoved { who: caller, device_id });
+	}
+
+	change_nickname {
+		let n in 3 .. T::MaxNicknameLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'b')?;
+		// the pending transfer of the old nickname is withdrawn
+		let to: T::AccountId = account("receiver", 0, SEED);
+		Template::<T>::transfer_nickname(RawOrigin::Signed(caller.clone()).into(), to)?;
+		let nickname: Nickname<T> = bytes(b'a', n);
+	}: _(RawOrigin::Signed(caller.clone()), nickname.clone())
+	verify {
+		assert_eq!(ItemByNicknameStore::<T>::get(&nickname), Some(caller.clone()));
+		assert_eq!(PendingNicknameTransfers::<T>::get(&caller), None);
+	}
+
+	transfer_nickname {
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'a')?;
+		let to: T::AccountId = account("receiver", 0, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), to.clone())
+	verify {
+		assert_eq!(PendingNicknameTransfers::<T>::get(&caller), Some(to));
+	}
+
+	cancel_nickname_transfer {
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'a')?;
+		let to: T::AccountId = account("receiver", 0, SEED);
+		Template::<T>::transfer_nickname(RawOrigin::Signed(caller.clone()).into(), to)?;
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert_eq!(PendingNicknameTransfers::<T>::get(&caller), None);
+	}
+
+	accept_nickname {
+		let caller = funded::<T>(whitelisted_caller());
+		let from = funded::<T>(account("owner", 0, SEED));
+		register_max::<T>(&from, b'a')?;
+		Template::<T>::transfer_nickname(RawOrigin::Signed(from.clone()).into(), caller.clone())?;
+		let (address, key_proof) = messaging_key::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), from.clone(), address, key_proof)
+	verify {
+		assert!(!ItemByAccountIdStore::<T>::contains_key(&from));
+		assert!(ItemByAccountIdStore::<T>::contains_key(&caller));
+	}
+
+	update_address {
+		let caller = funded::<T>(whitelisted_caller());
+		register_max::<T>(&caller, b'a')?;
+
+		// a full history has to drop its oldest entry
+		for _ in 0..T::MaxAddressHistory::get() {
+			let (address, key_proof) = messaging_key::<T>(&caller);
+			Template::<T>::update_address(RawOrigin::Signed(caller.clone()).into(), address, key_proof)?;
+		}
+		let (address, key_proof) = messaging_key::<T>(&caller);
+	}: _(RawOrigin::Signed(caller.clone()), address, key_proof)
+	verify {
+		assert_eq!(ItemByAccountIdStore::<T>::get(&caller).address, address);
+	}
+
+	reserve_nickname {
+		let n in 3 .. T::MaxNicknameLen::get();
+
+		let origin =
+			T::ReservedNicknameOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let nickname: Nickname<T> = bytes(b'a', n);
+	}: _<T::RuntimeOrigin>(origin, nickname.clone())
+	verify {
+		assert!(ReservedNicknames::<T>::contains_key(&nickname));
+	}
+
+	unreserve_nickname {
+		let n in 3 .. T::MaxNicknameLen::get();
+
+		let origin =
+			T::ReservedNicknameOrigin::try_successful_origin().map_err(|_| BenchmarkError::Weightless)?;
+		let nickname: Nickname<T> = bytes(b'a', n);
+		Template::<T>::reserve_nickname(origin.clone(), nickname.clone())?;
+	}: _<T::RuntimeOrigin>(origin, nickname.clone())
+	verify {
+		assert!(!ReservedNicknames::<T>::contains_key(&nickname));
+	}
+
+	add_ice_candidates {
+		let c in 0 .. T::MaxIceCandidatesLen::get();
+
+		let caller: T::AccountId = whitelisted_caller();
+		let offerer: T::AccountId = account("offerer", 0, SEED);
+		let session_id = NextSessionId::<T>::get();
+
+		// the offeree trickles candidates, so the session is only found by the second lookup
+		Template::<T>::offer_chat(
+			RawOrigin::Signed(offerer.clone()).into(),
+			bytes(1, 0),
+			bytes(1, 0),
+			caller.clone(),
+			None, None,
+		)?;
+		let candidates: IceCandidatesPayload<T> = bytes(1, c);
+	}: _(RawOrigin::Signed(caller.clone()), offerer.clone(), session_id, candidates.clone())
+	verify {
+		assert_last_event::<T>(
+			Event::IceCandidates { from: caller, to: offerer, session_id, candidates }
+		);
+	}
+
+	reject_chat {
+		let caller: T::AccountId = whitelisted_caller();
+		let offerer: T::AccountId = account("offerer", 0, SEED);
+		let session_id = NextSessionId::<T>::get();
+		Template::<T>::offer_chat(
+			RawOrigin::Signed(offerer.clone()).into(),
+			bytes(1, T::MaxWelcomeMsgLen::get()),
+			bytes(1, T::MaxOfferLen::get()),
+			caller.clone(),
+			None, None,
+		)?;
+	}: _(RawOrigin::Signed(caller.clone()), offerer.clone(), session_id, u8::MAX)
+	verify {
+		let session = ChatSessions::<T>::get((&offerer, &caller, session_id)).unwrap();
+		assert_eq!(session.state, SessionState::Rejected);
+	}
+
+	cancel_offer {
+		let caller: T::AccountId = whitelisted_caller();
+		let to: T::AccountId = account("offeree", 0, SEED);
+		let session_id = NextSessionId::<T>::get();
+		Template::<T>::offer_chat(
+			RawOrigin::Signed(caller.clone()).into(),
+			bytes(1, T::MaxWelcomeMsgLen::get()),
+			bytes(1, T::MaxOfferLen::get()),
+			to.clone(),
+			None, None,
+		)?;
+	}: _(RawOrigin::Signed(caller.clone()), to.clone(), session_id)
+	verify {
+		assert!(!ChatSessions::<T>::contains_key((&caller, &to, session_id)));
+	}
+
+	expire_offers {
+		let e in 0 .. T::MaxExpiriesPerBlock::get();
+
+		let due = offers_due::<T>(e)?;
+		frame_system::Pallet::<T>::set_block_number(due);
+	}: {
+		Template::<T>::on_initialize(due);
+	}
+	verify {
+		let (head, tail) = ExpiryQueueBounds::<T>::get();
+		assert_eq!(head, tail);
+		let (head, tail) = PruneQueueBounds::<T>::get();
+		assert_eq!(tail - head, e as u64);
+	}
+
+	prune_sessions {
+		let p in 0 .. T::MaxExpiriesPerBlock::get();
+
+		let due = offers_due::<T>(p)?;
+		frame_system::Pallet::<T>::set_block_number(due);
+		Template::<T>::on_initialize(due);
+	}: {
+		Template::<T>::on_idle(due, Weight::MAX);
+	}
+	verify {
+		let (head, tail) = PruneQueueBounds::<T>::get();
+		assert_eq!(head, tail);
+	}
+
+	prune_rate_limits {
+		let r in 0 .. T::MaxExpiriesPerBlock::get();
+
+		offers_due::<T>(r)?;
+		let over = frame_system::Pallet::<T>::block_number()
+			.saturating_add(T::OfferRateWindow::get());
+	}: {
+		Template::<T>::prune_rate_limits(over, Weight::MAX);
+	}
+	verify {
+		let (head, tail) = RateLimitQueueBounds::<T>::get();
+		assert_eq!(head, tail);
+		assert_eq!(OfferRateLimits::<T>::iter().count(), 0);
+	}
+
+	clear_feeless_usage {
+		let u in 0 .. T::FeelessEraCapacity::get();
+
+		for i in 0..u {
+			Template::<T>::note_feeless_call(&account("feeless", i, SEED));
+		}
+		let next_era = T::FeelessEra::get();
+		frame_system::Pallet::<T>::set_block_number(next_era);
+	}: {
+		Template::<T>::on_initialize(next_era);
+	}
+	verify {
+		assert_eq!(FeelessUsage::<T>::iter_prefix(T::BlockNumber::zero()).count(), 0);
+	}
+
+	block_account {
+		let caller = funded::<T>(whitelisted_caller());
+		let account: T::AccountId = account("blocked", 0, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), account.clone())
+	verify {
+		assert!(BlockedAccounts::<T>::contains_key(&caller, &account));
+	}
+
+	unblock_account {
+		let caller = funded::<T>(whitelisted_caller());
+		let account: T::AccountId = account("blocked", 0, SEED);
+		Template::<T>::block_account(RawOrigin::Signed(caller.clone()).into(), account.clone())?;
+	}: _(RawOrigin::Signed(caller.clone()), account.clone())
+	verify {
+		assert!(!BlockedAccounts::<T>::contains_key(&caller, &account));
+	}
+
+	set_inbound_policy {
+		let caller: T::AccountId = whitelisted_caller();
+	}: _(RawOrigin::Signed(caller.clone()), InboundPolicy::ContactsOnly)
+	verify {
+		assert_eq!(InboundPolicies::<T>::get(&caller), InboundPolicy::ContactsOnly);
+	}
+
+	set_contact_account {
+		let a in 0 .. T::MaxContactAddrLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let contact_addr: EncodedContactAddr<T> = bytes(1, a);
+		Template::<T>::upsert_contact(
+			RawOrigin::Signed(caller.clone()).into(),
+			envelope(1, T::MaxContactNameLen::get()),
+			contact_addr.clone(),
+		)?;
+
+		// relinking has to drop the old index entry
+		Template::<T>::set_contact_account(
+			RawOrigin::Signed(caller.clone()).into(),
+			contact_addr.clone(),
+			Some(account("contact", 0, SEED)),
+		)?;
+		let account: T::AccountId = account("contact", 1, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), contact_addr.clone(), Some(account.clone()))
+	verify {
+		assert_eq!(ContactIndex::<T>::get(&caller, &account), Some(contact_addr));
+	}
+
+	create_room {
+		let n in 0 .. T::MaxRoomNameLen::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let name: RoomName<T> = bytes(b'a', n);
+		let room_id = NextRoomId::<T>::get();
+	}: _(RawOrigin::Signed(caller.clone()), name.clone())
+	verify {
+		assert_last_event::<T>(Event::RoomCreated { room_id, creator: caller, name });
+	}
+
+	invite {
+		let caller = funded::<T>(whitelisted_caller());
+		let room_id = room_with_members::<T>(&caller, T::MaxRoomMembers::get() - 1)?;
+		let account: T::AccountId = account("invitee", 0, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), room_id, account.clone())
+	verify {
+		assert!(Rooms::<T>::get(room_id).unwrap().invited.contains(&account));
+	}
+
+	revoke_invite {
+		let caller = funded::<T>(whitelisted_caller());
+		let room_id = room_with_invites::<T>(&caller)?;
+		let account: T::AccountId = account("invitee", T::MaxRoomMembers::get() - 1, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), room_id, account.clone())
+	verify {
+		assert!(!Rooms::<T>::get(room_id).unwrap().invited.contains(&account));
+	}
+
+	decline_invite {
+		let admin = funded::<T>(account("admin", 0, SEED));
+		let room_id = room_with_invites::<T>(&admin)?;
+		let caller: T::AccountId = account("invitee", T::MaxRoomMembers::get() - 1, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), room_id)
+	verify {
+		assert!(!Rooms::<T>::get(room_id).unwrap().invited.contains(&caller));
+	}
+
+	join {
+		let caller = funded::<T>(whitelisted_caller());
+		let admin = funded::<T>(account("admin", 0, SEED));
+		let room_id = room_with_members::<T>(&admin, T::MaxRoomMembers::get() - 1)?;
+		Template::<T>::invite(RawOrigin::Signed(admin.clone()).into(), room_id, caller.clone())?;
+		post_room_key::<T>(&admin, room_id)?;
+	}: _(RawOrigin::Signed(caller.clone()), room_id)
+	verify {
+		assert!(Rooms::<T>::get(room_id).unwrap().member(&caller).is_some());
+	}
+
+	leave {
+		let caller = funded::<T>(whitelisted_caller());
+		let admin = funded::<T>(account("admin", 0, SEED));
+		let room_id = room_with_members::<T>(&admin, T::MaxRoomMembers::get() - 1)?;
+		Template::<T>::invite(RawOrigin::Signed(admin.clone()).into(), room_id, caller.clone())?;
+		Template::<T>::join(RawOrigin::Signed(caller.clone()).into(), room_id)?;
+		post_room_key::<T>(&admin, room_id)?;
+	}: _(RawOrigin::Signed(caller.clone()), room_id)
+	verify {
+		assert!(Rooms::<T>::get(room_id).unwrap().member(&caller).is_none());
+	}
+
+	kick {
+		let caller = funded::<T>(whitelisted_caller());
+		let room_id = room_with_members::<T>(&caller, T::MaxRoomMembers::get())?;
+		let account: T::AccountId = account("member", T::MaxRoomMembers::get() - 1, SEED);
+		post_room_key::<T>(&caller, room_id)?;
+	}: _(RawOrigin::Signed(caller.clone()), room_id, account.clone())
+	verify {
+		assert!(Rooms::<T>::get(room_id).unwrap().member(&account).is_none());
+	}
+
+	set_admin {
+		let caller = funded::<T>(whitelisted_caller());
+		let room_id = room_with_members::<T>(&caller, T::MaxRoomMembers::get())?;
+		let account: T::AccountId = account("member", T::MaxRoomMembers::get() - 1, SEED);
+	}: _(RawOrigin::Signed(caller.clone()), room_id, account.clone(), true)
+	verify {
+		assert!(Rooms::<T>::get(room_id).unwrap().is_admin(&account));
+	}
+
+	set_room_key {
+		let m in 1 .. T::MaxRoomMembers::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let room_id = room_with_members::<T>(&caller, m)?;
+		let epoch = RoomKeyEpochs::<T>::get(room_id);
+
+		// replacing the keys of the epoch has to release the old deposit
+		post_room_key::<T>(&caller, room_id)?;
+	}: _(RawOrigin::Signed(caller.clone()), room_id, epoch, room_key_copies::<T>(room_id))
+	verify {
+		assert_last_event::<T>(Event::RoomKeySet { room_id, epoch, by: caller });
+	}
+
+	publish_prekeys {
+		let k in 0 .. T::MaxOneTimePrekeys::get();
+
+		let caller = funded::<T>(whitelisted_caller());
+		let (address, key_proof) = messaging_key::<T>(&caller);
+		Template::<T>::register(
+			RawOrigin::Signed(caller.clone()).into(),
+			bytes(b'a', T::MaxNicknameLen::get()),
+			address,
+			key_proof,
+		)?;
+		// adding to prekeys which were not claimed yet
+		Template::<T>::publish_prekeys(
+			RawOrigin::Signed(caller.clone()).into(),
+			signed_prekey(address),
+			prekeys::<T>(T::MaxOneTimePrekeys::get() - k),
+		)?;
+	}: _(RawOrigin::Signed(caller.clone()), signed_prekey(address), prekeys::<T>(k))
+	verify {
+		assert_last_event::<T>(Event::PrekeysPublished {
+			who: caller,
+			one_time_prekeys: T::MaxOneTimePrekeys::get(),
+		});
+	}
+
+	claim_one_time_prekey {
 		let caller: T::AccountId = whitelisted_caller();
-	}: _(RawOrigin::Signed(caller), s)
+		let target = funded::<T>(account("target", 0, SEED));
+		register_max::<T>(&target, b'a')?;
+	}: _(RawOrigin::Signed(caller.clone()), target.clone())
 	verify {
-		assert_eq!(Something::<T>::get(), Some(s));
+		let remaining = PrekeyBundles::<T>::get(&target).unwrap().one_time_prekeys.len() as u32;
+		assert_eq!(remaining, T::MaxOneTimePrekeys::get() - 1);
 	}
 
 	impl_benchmark_test_suite!(Template, crate::mock::new_test_ext(), crate::mock::Test);
@@ -0,0 +1,192 @@
+//! Fee payment which lets a few chat signals per era through for free with a proof of work.
+
+use crate::{Call, Config, Pallet};
+use codec::{Decode, Encode};
+use frame_support::{
+	sp_io::hashing::blake2_256,
+	sp_runtime::{
+		traits::{DispatchInfoOf, One, PostDispatchInfoOf, Saturating, SignedExtension, Zero},
+		transaction_validity::{
+			InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+		},
+		DispatchResult, RuntimeDebug,
+	},
+	sp_std::fmt,
+	traits::{Get, IsSubType},
+	CloneNoBound, EqNoBound, PartialEqNoBound,
+};
+use scale_info::TypeInfo;
+
+/// Custom validity error of a feeless call of an account which used up its feeless calls.
+pub const FEELESS_QUOTA_EXHAUSTED: u8 = 2;
+/// Custom validity error of a proof of work over an unknown or too old block.
+pub const UNKNOWN_POW_BLOCK: u8 = 3;
+/// Custom validity error of a proof of work which doesn't meet the difficulty.
+pub const INSUFFICIENT_POW: u8 = 4;
+/// Custom validity error of a feeless call once all accounts used up the feeless calls of an era.
+pub const FEELESS_CAPACITY_EXHAUSTED: u8 = 5;
+
+/// Proof of work over `blake2_256((sender, account nonce, hash of block_number, nonce))`.
+///
+/// The account nonce is the one the transaction is signed with, so a proof can't be used twice.
+#[derive(Clone, Encode, Decode, Eq, PartialEq, RuntimeDebug, TypeInfo)]
+pub struct FeelessProof<BlockNumber> {
+	/// Recent block the proof is bound to, at most `FeelessProofLifetime` blocks old.
+	pub block_number: BlockNumber,
+	pub nonce: u64,
+}
+
+/// Wraps the fee payment extension `S`, letting `offer_chat` and `answer_chat` calls which carry
+/// a valid proof of work pass without it while the sender and the era have feeless calls left.
+///
+/// Transactions without a proof are handed over to `S` as is. The extension has to come after
+/// `frame_system::CheckNonce` in the signed extensions, which bumps the account nonce before
+/// `pre_dispatch` of this one runs.
+#[derive(CloneNoBound, Encode, Decode, EqNoBound, PartialEqNoBound, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct ChargeOrFeeless<T: Config + Send + Sync, S> {
+	pub inner: S,
+	pub proof: Option<FeelessProof<T::BlockNumber>>,
+}
+
+impl<T: Config + Send + Sync, S> ChargeOrFeeless<T, S> {
+	pub fn new(inner: S, proof: Option<FeelessProof<T::BlockNumber>>) -> Self {
+		Self { inner, proof }
+	}
+}
+
+impl<T: Config + Send + Sync, S> From<S> for ChargeOrFeeless<T, S> {
+	fn from(inner: S) -> Self {
+		Self::new(inner, None)
+	}
+}
+
+impl<T: Config + Send + Sync, S: fmt::Debug> fmt::Debug for ChargeOrFeeless<T, S> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "ChargeOrFeeless({:?}, {:?})", self.inner, self.proof)
+	}
+}
+
+impl<T: Config + Send + Sync, S> ChargeOrFeeless<T, S>
+where
+	T::RuntimeCall: IsSubType<Call<T>>,
+{
+	fn check_feeless(
+		who: &T::AccountId,
+		account_nonce: T::Index,
+		call: &T::RuntimeCall,
+		proof: &FeelessProof<T::BlockNumber>,
+	) -> Result<(), TransactionValidityError> {
+		if !matches!(call.is_sub_type(), Some(Call::offer_chat { .. } | Call::answer_chat { .. })) {
+			return Err(InvalidTransaction::Call.into())
+		}
+
+		if Pallet::<T>::feeless_calls_left(who) == 0 {
+			return Err(InvalidTransaction::Custom(FEELESS_QUOTA_EXHAUSTED).into())
+		}
+
+		if Pallet::<T>::feeless_capacity_left() == 0 {
+			return Err(InvalidTransaction::Custom(FEELESS_CAPACITY_EXHAUSTED).into())
+		}
+
+		// the genesis hash is never pruned and others only after `BlockHashCount` blocks
+		let now = <frame_system::Pallet<T>>::block_number();
+		if proof.block_number.is_zero() ||
+			proof.block_number.saturating_add(T::FeelessProofLifetime::get()) < now
+		{
+			return Err(InvalidTransaction::Custom(UNKNOWN_POW_BLOCK).into())
+		}
+
+		let block_hash = <frame_system::Pallet<T>>::block_hash(proof.block_number);
+		if block_hash == Default::default() {
+			return Err(InvalidTransaction::Custom(UNKNOWN_POW_BLOCK).into())
+		}
+
+		let work = (who, account_nonce, block_hash, proof.nonce).using_encoded(blake2_256);
+		if leading_zero_bits(&work) < T::FeelessPowDifficulty::get() {
+			return Err(InvalidTransaction::Custom(INSUFFICIENT_POW).into())
+		}
+
+		Ok(())
+	}
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+	let mut bits = 0;
+	for byte in hash {
+		bits += byte.leading_zeros();
+		if *byte != 0 {
+			break
+		}
+	}
+	bits
+}
+
+impl<T, S> SignedExtension for ChargeOrFeeless<T, S>
+where
+	T: Config + Send + Sync,
+	T::RuntimeCall: IsSubType<Call<T>>,
+	S: SignedExtension<AccountId = T::AccountId, Call = T::RuntimeCall>,
+{
+	const IDENTIFIER: &'static str = "ChargeOrFeeless";
+	type AccountId = T::AccountId;
+	type Call = T::RuntimeCall;
+	type AdditionalSigned = S::AdditionalSigned;
+	// `None` for feeless calls
+	type Pre = Option<S::Pre>;
+
+	fn additional_signed(&self) -> Result<Self::AdditionalSigned, TransactionValidityError> {
+		self.inner.additional_signed()
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> TransactionValidity {
+		match &self.proof {
+			Some(proof) => {
+				let account_nonce = <frame_system::Pallet<T>>::account_nonce(who);
+				Self::check_feeless(who, account_nonce, call, proof)?;
+				Ok(ValidTransaction::default())
+			},
+			None => self.inner.validate(who, call, info, len),
+		}
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<Self::Pre, TransactionValidityError> {
+		match self.proof {
+			Some(proof) => {
+				// `CheckNonce` already counted the transaction
+				let account_nonce =
+					<frame_system::Pallet<T>>::account_nonce(who).saturating_sub(One::one());
+				Self::check_feeless(who, account_nonce, call, &proof)?;
+				Pallet::<T>::note_feeless_call(who);
+				Ok(None)
+			},
+			None => self.inner.pre_dispatch(who, call, info, len).map(Some),
+		}
+	}
+
+	fn post_dispatch(
+		pre: Option<Self::Pre>,
+		info: &DispatchInfoOf<Self::Call>,
+		post_info: &PostDispatchInfoOf<Self::Call>,
+		len: usize,
+		result: &DispatchResult,
+	) -> Result<(), TransactionValidityError> {
+		match pre {
+			Some(None) => Ok(()),
+			Some(Some(pre)) => S::post_dispatch(Some(pre), info, post_info, len, result),
+			None => S::post_dispatch(None, info, post_info, len, result),
+		}
+	}
+}
@@ -14,16 +14,41 @@ mod tests;
 #[cfg(feature = "runtime-benchmarks")]
 mod benchmarking;
 
+pub mod feeless;
+pub mod migrations;
+pub mod nickname;
+pub mod rate_limit;
+pub mod weights;
+
+pub use feeless::ChargeOrFeeless;
+pub use nickname::NicknameValidator;
+pub use pallet_template_envelope::EncryptedEnvelope;
+pub use rate_limit::CheckOfferRateLimit;
+pub use weights::WeightInfo;
+
 #[frame_support::pallet]
 pub mod pallet {
 	use frame_support::{
 		pallet_prelude::{DispatchResult, OptionQuery, StorageMap, *},
-		Blake2_128Concat,
+		sp_io,
+		sp_runtime::{
+			app_crypto::ed25519,
+			traits::{CheckedDiv, Saturating, Zero},
+		},
+		sp_std::{self, vec::Vec},
+		traits::{Currency, ReservableCurrency},
+		Blake2_128Concat, BoundedBTreeMap,
 	};
 	use frame_system::pallet_prelude::{OriginFor, *};
 
+	use crate::{NicknameValidator, WeightInfo};
+
+	/// The current storage version.
+	const STORAGE_VERSION: StorageVersion = StorageVersion::new(11);
+
 	#[pallet::pallet]
 	#[pallet::generate_store(pub(super) trait Store)]
+	#[pallet::storage_version(STORAGE_VERSION)]
 	pub struct Pallet<T>(_);
 
 	/// Configure the pallet by specifying the parameters and types on which it depends.
@@ -31,22 +56,233 @@ pub mod pallet {
 	pub trait Config: frame_system::Config {
 		/// Because this pallet emits events, it depends on the runtime's definition of an event.
 		type RuntimeEvent: From<Event<Self>> + IsType<<Self as frame_system::Config>::RuntimeEvent>;
+
+		/// Number of blocks a chat offer stays open before it expires.
+		#[pallet::constant]
+		type OfferTtl: Get<Self::BlockNumber>;
+
+		/// Maximum number of offers expired in the same block, offers coming due beyond it are
+		/// expired in the following blocks.
+		#[pallet::constant]
+		type MaxExpiriesPerBlock: Get<u32>;
+
+		/// Maximum number of chat offers an account can make within `OfferRateWindow` blocks.
+		#[pallet::constant]
+		type MaxOffersPerWindow: Get<u32>;
+
+		/// Number of blocks the offers of an account are counted in.
+		#[pallet::constant]
+		type OfferRateWindow: Get<Self::BlockNumber>;
+
+		/// Number of blocks in an era of feeless calls.
+		#[pallet::constant]
+		type FeelessEra: Get<Self::BlockNumber>;
+
+		/// Number of `offer_chat` and `answer_chat` calls an account can make without fees in an
+		/// era of feeless calls.
+		#[pallet::constant]
+		type MaxFeelessPerEra: Get<u32>;
+
+		/// Number of feeless calls all accounts together can make in an era of feeless calls,
+		/// which bounds the state fresh keypairs can add without paying fees.
+		#[pallet::constant]
+		type FeelessEraCapacity: Get<u32>;
+
+		/// Number of leading zero bits the proof of work of a feeless call needs.
+		#[pallet::constant]
+		type FeelessPowDifficulty: Get<u32>;
+
+		/// Number of blocks the proof of work of a feeless call stays valid for, which must not
+		/// exceed `BlockHashCount`.
+		#[pallet::constant]
+		type FeelessProofLifetime: Get<Self::BlockNumber>;
+
+		/// Maximum number of members of a room, which is also the maximum number of its pending
+		/// invites.
+		#[pallet::constant]
+		type MaxRoomMembers: Get<u32>;
+
+		/// Maximum length of a room name.
+		#[pallet::constant]
+		type MaxRoomNameLen: Get<u32>;
+
+		/// Maximum length of a room key encrypted to a member.
+		#[pallet::constant]
+		type MaxRoomKeyLen: Get<u32>;
+
+		/// Maximum length of an SDP offer.
+		#[pallet::constant]
+		type MaxOfferLen: Get<u32>;
+
+		/// Maximum length of a welcome message sent along with an offer.
+		#[pallet::constant]
+		type MaxWelcomeMsgLen: Get<u32>;
+
+		/// Maximum length of an SDP answer.
+		#[pallet::constant]
+		type MaxAnswerLen: Get<u32>;
+
+		/// Maximum length of a batch of trickled ICE candidates.
+		#[pallet::constant]
+		type MaxIceCandidatesLen: Get<u32>;
+
+		/// Maximum length of an encoded contact name.
+		#[pallet::constant]
+		type MaxContactNameLen: Get<u32>;
+
+		/// Maximum length of an encoded contact address.
+		#[pallet::constant]
+		type MaxContactAddrLen: Get<u32>;
+
+		/// Maximum number of contacts an account can store.
+		#[pallet::constant]
+		type MaxContacts: Get<u32>;
+
+		/// Maximum number of contacts upserted or removed by a single batch call.
+		#[pallet::constant]
+		type MaxContactBatch: Get<u32>;
+
+		/// Maximum number of contact groups of an account, which is also the maximum number of
+		/// groups a contact can be in.
+		#[pallet::constant]
+		type MaxContactGroups: Get<u32>;
+
+		/// Maximum length of an encoded contact group name.
+		#[pallet::constant]
+		type MaxContactGroupNameLen: Get<u32>;
+
+		/// Maximum length of an encoded contact note.
+		#[pallet::constant]
+		type MaxContactNoteLen: Get<u32>;
+
+		/// Maximum length of a nickname.
+		#[pallet::constant]
+		type MaxNicknameLen: Get<u32>;
+
+		/// The currency in which storage deposits are reserved.
+		type Currency: ReservableCurrency<Self::AccountId>;
+
+		/// Deposit reserved for every stored contact or nickname registration.
+		#[pallet::constant]
+		type DepositPerItem: Get<BalanceOf<Self>>;
+
+		/// Deposit reserved for every byte of a stored contact or nickname registration.
+		#[pallet::constant]
+		type DepositPerByte: Get<BalanceOf<Self>>;
+
+		/// Number of previous addresses kept per account after a key rotation.
+		#[pallet::constant]
+		type MaxAddressHistory: Get<u32>;
+
+		/// Maximum number of one-time prekeys an account can have published at once.
+		#[pallet::constant]
+		type MaxOneTimePrekeys: Get<u32>;
+
+		/// Number of one-time prekeys below which an account is asked to publish more.
+		#[pallet::constant]
+		type MinOneTimePrekeys: Get<u32>;
+
+		/// Maximum number of devices an account can receive chat offers on.
+		#[pallet::constant]
+		type MaxDevices: Get<u32>;
+
+		/// Maximum length of a device label.
+		#[pallet::constant]
+		type MaxDeviceLabelLen: Get<u32>;
+
+		/// Rules a nickname has to follow, applied before it is looked up or stored.
+		type NicknameValidator: NicknameValidator;
+
+		/// Origin allowed to reserve nicknames which ordinary users can't claim.
+		type ReservedNicknameOrigin: EnsureOrigin<Self::RuntimeOrigin>;
+
+		/// Weight information for extrinsics in this pallet.
+		type WeightInfo: WeightInfo;
 	}
 
-	#[derive(Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
-	pub struct ContactByAccountId {
-		// encoded name
-		pub name: EncodedContactName,
+	pub type BalanceOf<T> =
+		<<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
+	pub type OfferPayload<T> = BoundedVec<u8, <T as Config>::MaxOfferLen>;
+	pub type WelcomeMsg<T> = BoundedVec<u8, <T as Config>::MaxWelcomeMsgLen>;
+	pub type AnswerPayload<T> = BoundedVec<u8, <T as Config>::MaxAnswerLen>;
+	pub type IceCandidatesPayload<T> = BoundedVec<u8, <T as Config>::MaxIceCandidatesLen>;
+	pub type RoomName<T> = BoundedVec<u8, <T as Config>::MaxRoomNameLen>;
+	pub type EncodedContactName<T> = BoundedVec<u8, <T as Config>::MaxContactNameLen>;
+	pub type EncodedContactAddr<T> = BoundedVec<u8, <T as Config>::MaxContactAddrLen>;
+	pub type ContactEnvelope<T> = EncryptedEnvelope<EncodedContactName<T>>;
+	pub type ContactBatch<T> =
+		BoundedVec<(ContactEnvelope<T>, EncodedContactAddr<T>), <T as Config>::MaxContactBatch>;
+	pub type ContactAddrBatch<T> =
+		BoundedVec<EncodedContactAddr<T>, <T as Config>::MaxContactBatch>;
+	pub type Nickname<T> = BoundedVec<u8, <T as Config>::MaxNicknameLen>;
+
+	pub type GroupId = u32;
+	pub type ContactGroupName<T> =
+		EncryptedEnvelope<BoundedVec<u8, <T as Config>::MaxContactGroupNameLen>>;
+	pub type ContactNote<T> = EncryptedEnvelope<BoundedVec<u8, <T as Config>::MaxContactNoteLen>>;
+
+	/// Bit set of markers clients attach to a contact.
+	pub type ContactFlags = u32;
+	/// Contact is starred as a favourite.
+	pub const CONTACT_FAVOURITE: ContactFlags = 1 << 0;
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		DefaultNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct ContactByAccountId<T: Config> {
+		// name sealed by the owner's clients
+		pub name: ContactEnvelope<T>,
+		// account the contact is linked to, indexed in `ContactIndex`
+		pub account: Option<T::AccountId>,
+		// groups of the owner's `ContactGroupsOf` entry the contact is in
+		pub groups: BoundedVec<GroupId, T::MaxContactGroups>,
+		pub flags: ContactFlags,
+		// note sealed by the owner's clients
+		pub note: Option<ContactNote<T>>,
+		// amount reserved for storing the contact
+		pub deposit: BalanceOf<T>,
 	}
 
-	impl Default for ContactByAccountId {
-		fn default() -> Self {
-			ContactByAccountId { name: [0_u8; 1000] }
+	impl<T: Config> ContactByAccountId<T> {
+		pub fn is_favourite(&self) -> bool {
+			self.flags & CONTACT_FAVOURITE != 0
 		}
 	}
 
-	pub type EncodedContactName = [u8; 1000];
-	pub type EncodedContactAddr = [u8; 1000];
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		DefaultNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct ContactGroups<T: Config> {
+		pub groups: BoundedBTreeMap<GroupId, ContactGroupName<T>, T::MaxContactGroups>,
+		// ids are never reused, so contacts still in a removed group don't end up in a new one
+		pub next_id: GroupId,
+		// amount reserved for the group names
+		pub deposit: BalanceOf<T>,
+	}
+
+	/// Number of contacts returned per page by `contacts_of`.
+	pub const CONTACTS_PAGE_SIZE: u32 = 100;
 
 	#[pallet::storage]
 	#[pallet::getter(fn get_contact_by_account_id)]
@@ -55,28 +291,444 @@ pub mod pallet {
 		Blake2_128Concat,
 		T::AccountId,
 		Blake2_128Concat,
-		EncodedContactAddr,
-		ContactByAccountId,
+		EncodedContactAddr<T>,
+		ContactByAccountId<T>,
 		ValueQuery,
 	>;
 
+	/// Number of contacts stored by an account.
+	#[pallet::storage]
+	#[pallet::getter(fn get_contact_count)]
+	pub type ContactCount<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+	/// Contacts linked to an account, keyed by their owner and the linked account.
+	#[pallet::storage]
+	#[pallet::getter(fn get_contact_by_linked_account)]
+	pub type ContactIndex<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		T::AccountId,
+		EncodedContactAddr<T>,
+		OptionQuery,
+	>;
+
+	/// Named groups an account sorts its contacts into.
+	#[pallet::storage]
+	#[pallet::getter(fn get_contact_groups)]
+	pub type ContactGroupsOf<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, ContactGroups<T>, ValueQuery>;
+
+	/// Accounts an account accepts chat offers from.
 	#[derive(
-		Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, Default, TypeInfo,
+		Clone, Copy, Default, Encode, Decode, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo,
 	)]
-	pub struct ItemByAccountId {
+	pub enum InboundPolicy {
+		#[default]
+		Everyone,
+		/// Only accounts linked to one of the receiver's contacts
+		ContactsOnly,
+		Nobody,
+	}
+
+	#[pallet::storage]
+	#[pallet::getter(fn get_inbound_policy)]
+	pub type InboundPolicies<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, InboundPolicy, ValueQuery>;
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		DefaultNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct ItemByAccountId<T: Config> {
 		pub address: [u8; 32],
-		pub nickname: [u8; 21],
+		pub nickname: Nickname<T>,
+		// amount reserved for storing the registration
+		pub deposit: BalanceOf<T>,
 	}
 
 	#[pallet::storage]
 	#[pallet::getter(fn get_address_by_nickname)]
 	pub type ItemByNicknameStore<T: Config> =
-		StorageMap<_, Blake2_128Concat, [u8; 21], T::AccountId, OptionQuery>;
+		StorageMap<_, Blake2_128Concat, Nickname<T>, T::AccountId, OptionQuery>;
 
 	#[pallet::storage]
 	#[pallet::getter(fn get_address_by_account_id)]
 	pub type ItemByAccountIdStore<T: Config> =
-		StorageMap<_, Blake2_128Concat, T::AccountId, ItemByAccountId, ValueQuery>;
+		StorageMap<_, Blake2_128Concat, T::AccountId, ItemByAccountId<T>, ValueQuery>;
+
+	/// Nicknames which can't be claimed by ordinary users.
+	#[pallet::storage]
+	#[pallet::getter(fn is_nickname_reserved)]
+	pub type ReservedNicknames<T: Config> =
+		StorageMap<_, Blake2_128Concat, Nickname<T>, (), OptionQuery>;
+
+	/// Previous addresses of an account along with the block they were replaced in, oldest first.
+	#[pallet::storage]
+	#[pallet::getter(fn get_address_history)]
+	pub type AddressHistory<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<([u8; 32], T::BlockNumber), T::MaxAddressHistory>,
+		ValueQuery,
+	>;
+
+	/// Number of messaging keys bound to an account so far, signed along with the key so that a
+	/// proof can't be replayed.
+	#[pallet::storage]
+	#[pallet::getter(fn get_key_binding_nonce)]
+	pub type KeyBindingNonces<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, u64, ValueQuery>;
+
+	/// Nickname transfers waiting to be accepted, keyed by the current owner.
+	#[pallet::storage]
+	#[pallet::getter(fn get_pending_nickname_transfer)]
+	pub type PendingNicknameTransfers<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, T::AccountId, OptionQuery>;
+
+	/// Accounts blocked by an account along with the deposit reserved for the entry.
+	///
+	/// The blocked account is hashed without being appended to the key, so the list can't be
+	/// enumerated, only checked for a given sender.
+	#[pallet::storage]
+	pub type BlockedAccounts<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128,
+		T::AccountId,
+		BalanceOf<T>,
+		OptionQuery,
+	>;
+
+	pub type SessionId = u64;
+
+	#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+	pub enum SessionState {
+		Pending,
+		Answered,
+		Rejected,
+		Expired,
+	}
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct ChatSession<T: Config> {
+		pub offer: OfferPayload<T>,
+		pub welcome_msg: WelcomeMsg<T>,
+		pub state: SessionState,
+		// block the offer was made in
+		pub created_at: T::BlockNumber,
+		// room the offer was made in
+		pub room: Option<RoomId>,
+		// device of the offeree the offer is for, all of them if `None`
+		pub device: Option<DeviceId>,
+	}
+
+	pub type SessionKey<AccountId> = (AccountId, AccountId, SessionId);
+
+	/// Id to be assigned to the next chat offer.
+	#[pallet::storage]
+	pub type NextSessionId<T: Config> = StorageValue<_, SessionId, ValueQuery>;
+
+	/// Chat sessions keyed by (offerer, offeree, session id), so that clients which were offline
+	/// when `Offer` was emitted can still find their pending offers.
+	#[pallet::storage]
+	#[pallet::getter(fn get_chat_session)]
+	pub type ChatSessions<T: Config> = StorageNMap<
+		_,
+		(
+			NMapKey<Blake2_128Concat, T::AccountId>,
+			NMapKey<Blake2_128Concat, T::AccountId>,
+			NMapKey<Twox64Concat, SessionId>,
+		),
+		ChatSession<T>,
+		OptionQuery,
+	>;
+
+	/// Pending offers keyed by offeree, then (offerer, session id), indexing `ChatSessions` for
+	/// `pending_offers`.
+	#[pallet::storage]
+	pub type PendingOffersTo<T: Config> = StorageDoubleMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		Blake2_128Concat,
+		(T::AccountId, SessionId),
+		(),
+		OptionQuery,
+	>;
+
+	/// Start of the current rate limit window of an account and the number of offers it made in it.
+	/// Removed in `on_idle` once the window is over.
+	#[pallet::storage]
+	pub type OfferRateLimits<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, (T::BlockNumber, u32), ValueQuery>;
+
+	/// Rate limit windows in the order they were started, along with the block they end at.
+	#[pallet::storage]
+	pub type RateLimitQueue<T: Config> =
+		StorageMap<_, Twox64Concat, u64, (T::BlockNumber, T::AccountId), OptionQuery>;
+
+	/// (head, tail) of the `RateLimitQueue`.
+	#[pallet::storage]
+	pub type RateLimitQueueBounds<T: Config> = StorageValue<_, (u64, u64), ValueQuery>;
+
+	/// Number of feeless calls an account made in an era of feeless calls. Entries of an era are
+	/// removed when the next one starts.
+	#[pallet::storage]
+	pub type FeelessUsage<T: Config> = StorageDoubleMap<
+		_,
+		Twox64Concat,
+		T::BlockNumber,
+		Blake2_128Concat,
+		T::AccountId,
+		u32,
+		ValueQuery,
+	>;
+
+	/// Era of feeless calls the `FeelessUsage` entries belong to and the number of feeless calls
+	/// all accounts made in it.
+	#[pallet::storage]
+	pub type FeelessCalls<T: Config> = StorageValue<_, (T::BlockNumber, u32), ValueQuery>;
+
+	/// Offers in the order they were made, along with the block they expire at.
+	#[pallet::storage]
+	pub type ExpiryQueue<T: Config> =
+		StorageMap<_, Twox64Concat, u64, (T::BlockNumber, SessionKey<T::AccountId>), OptionQuery>;
+
+	/// (head, tail) of the `ExpiryQueue`.
+	#[pallet::storage]
+	pub type ExpiryQueueBounds<T: Config> = StorageValue<_, (u64, u64), ValueQuery>;
+
+	/// Expired sessions waiting to be removed in `on_idle`.
+	#[pallet::storage]
+	pub type PruneQueue<T: Config> =
+		StorageMap<_, Twox64Concat, u64, SessionKey<T::AccountId>, OptionQuery>;
+
+	/// (head, tail) of the `PruneQueue`.
+	#[pallet::storage]
+	pub type PruneQueueBounds<T: Config> = StorageValue<_, (u64, u64), ValueQuery>;
+
+	/// Medium-term prekey, signed by the identity key published as the account address.
+	#[derive(Clone, Encode, Decode, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+	pub struct SignedPrekey {
+		pub key: [u8; 32],
+		/// Ed25519 signature of `key` by the identity key.
+		pub signature: [u8; 64],
+	}
+
+	pub type OneTimePrekeys<T> = BoundedVec<[u8; 32], <T as Config>::MaxOneTimePrekeys>;
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct PrekeyBundle<T: Config> {
+		pub signed_prekey: SignedPrekey,
+		// handed out one at a time, oldest first
+		pub one_time_prekeys: OneTimePrekeys<T>,
+		// amount reserved from the owner for the bundle
+		pub deposit: BalanceOf<T>,
+	}
+
+	/// Prekeys published by registered accounts, so that chat sessions can be set up while the
+	/// offeree is offline.
+	#[pallet::storage]
+	#[pallet::getter(fn get_prekey_bundle)]
+	pub type PrekeyBundles<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, PrekeyBundle<T>, OptionQuery>;
+
+	pub type DeviceId = u32;
+	pub type DeviceLabel<T> = BoundedVec<u8, <T as Config>::MaxDeviceLabelLen>;
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct Device<T: Config> {
+		pub id: DeviceId,
+		// messaging key of the device
+		pub public_key: [u8; 32],
+		pub label: DeviceLabel<T>,
+		// block the device was added in
+		pub added_at: T::BlockNumber,
+		// amount reserved from the owner for the entry
+		pub deposit: BalanceOf<T>,
+	}
+
+	/// Devices of registered accounts, each with its own messaging key, so that a private key
+	/// doesn't have to be shared between them.
+	#[pallet::storage]
+	#[pallet::getter(fn get_devices)]
+	pub type Devices<T: Config> = StorageMap<
+		_,
+		Blake2_128Concat,
+		T::AccountId,
+		BoundedVec<Device<T>, T::MaxDevices>,
+		ValueQuery,
+	>;
+
+	/// Id to be assigned to the next device of an account. Ids are never reused, so an offer
+	/// can't reach a device added after the targeted one was removed.
+	#[pallet::storage]
+	pub type NextDeviceId<T: Config> =
+		StorageMap<_, Blake2_128Concat, T::AccountId, DeviceId, ValueQuery>;
+
+	pub type RoomId = u64;
+
+	/// Role of a room member.
+	#[derive(Clone, Copy, Encode, Decode, Eq, PartialEq, MaxEncodedLen, RuntimeDebug, TypeInfo)]
+	pub enum RoomRole {
+		Member,
+		/// Can invite, kick and promote members
+		Admin,
+	}
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct RoomMember<T: Config> {
+		pub account: T::AccountId,
+		pub role: RoomRole,
+		// amount reserved from the member for its entry
+		pub deposit: BalanceOf<T>,
+	}
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct Room<T: Config> {
+		pub name: RoomName<T>,
+		pub creator: T::AccountId,
+		pub members: BoundedVec<RoomMember<T>, T::MaxRoomMembers>,
+		// accounts which were invited but didn't join yet
+		pub invited: BoundedVec<T::AccountId, T::MaxRoomMembers>,
+		// amount reserved from the creator for the room name
+		pub deposit: BalanceOf<T>,
+	}
+
+	impl<T: Config> Room<T> {
+		pub fn member(&self, who: &T::AccountId) -> Option<&RoomMember<T>> {
+			self.members.iter().find(|member| &member.account == who)
+		}
+
+		pub fn is_admin(&self, who: &T::AccountId) -> bool {
+			self.member(who).map_or(false, |member| member.role == RoomRole::Admin)
+		}
+
+		fn has_admin(&self) -> bool {
+			self.members.iter().any(|member| member.role == RoomRole::Admin)
+		}
+	}
+
+	/// Counter of room key epochs, bumped whenever the members of a room change.
+	pub type RoomKeyEpoch = u32;
+
+	/// Symmetric room key encrypted to a member.
+	pub type RoomKeyCiphertext<T> = BoundedVec<u8, <T as Config>::MaxRoomKeyLen>;
+
+	/// Copies of a room key for each member.
+	pub type RoomKeyCopies<T> = BoundedBTreeMap<
+		<T as frame_system::Config>::AccountId,
+		RoomKeyCiphertext<T>,
+		<T as Config>::MaxRoomMembers,
+	>;
+
+	#[derive(
+		CloneNoBound,
+		Encode,
+		Decode,
+		EqNoBound,
+		PartialEqNoBound,
+		MaxEncodedLen,
+		RuntimeDebugNoBound,
+		TypeInfo,
+	)]
+	#[scale_info(skip_type_params(T))]
+	#[codec(mel_bound())]
+	pub struct RoomKey<T: Config> {
+		pub posted_by: T::AccountId,
+		pub keys: RoomKeyCopies<T>,
+		// amount reserved from the admin who posted the keys
+		pub deposit: BalanceOf<T>,
+	}
+
+	/// Id to be assigned to the next room.
+	#[pallet::storage]
+	pub type NextRoomId<T: Config> = StorageValue<_, RoomId, ValueQuery>;
+
+	/// Group chat rooms with their members.
+	#[pallet::storage]
+	#[pallet::getter(fn get_room)]
+	pub type Rooms<T: Config> = StorageMap<_, Twox64Concat, RoomId, Room<T>, OptionQuery>;
+
+	/// Current key epoch of each room.
+	#[pallet::storage]
+	#[pallet::getter(fn get_room_key_epoch)]
+	pub type RoomKeyEpochs<T: Config> =
+		StorageMap<_, Twox64Concat, RoomId, RoomKeyEpoch, ValueQuery>;
+
+	/// Room key of the current epoch, encrypted to each member. Dropped when the epoch rotates.
+	#[pallet::storage]
+	#[pallet::getter(fn get_room_key)]
+	pub type RoomKeys<T: Config> = StorageMap<_, Twox64Concat, RoomId, RoomKey<T>, OptionQuery>;
 
 	// Pallets use events to inform users when important changes are made.
 	// https://docs.substrate.io/main-docs/build/events-errors/
@@ -86,16 +738,97 @@ pub mod pallet {
 		/// Event documentation should end with an array that provides descriptive names for event
 		/// parameters. [something, who]
 		Offer {
-			offer: [u8; 2048],
+			offer: OfferPayload<T>,
 			offered_by: T::AccountId,
 			offered_to: T::AccountId,
-			welcome_msg: [u8; 300],
+			welcome_msg: WelcomeMsg<T>,
+			session_id: SessionId,
+			room: Option<RoomId>,
+			device: Option<DeviceId>,
 		},
 		Answer {
-			answer: [u8; 2048],
+			answer: AnswerPayload<T>,
 			answer_from: T::AccountId,
 			answer_to: T::AccountId,
+			session_id: SessionId,
 		},
+		/// Offer was not answered in time
+		OfferExpired { offered_by: T::AccountId, offered_to: T::AccountId, session_id: SessionId },
+		/// Receiver declined the offer
+		OfferRejected {
+			offered_by: T::AccountId,
+			offered_to: T::AccountId,
+			session_id: SessionId,
+			reason_code: u8,
+		},
+		/// Offerer withdrew the offer
+		OfferCancelled { offered_by: T::AccountId, offered_to: T::AccountId, session_id: SessionId },
+		/// ICE candidates were trickled to the other side of a session
+		IceCandidates {
+			from: T::AccountId,
+			to: T::AccountId,
+			session_id: SessionId,
+			candidates: IceCandidatesPayload<T>,
+		},
+		/// Account released its nickname
+		Unregistered { who: T::AccountId, nickname: Nickname<T> },
+		/// Account changed its nickname
+		NicknameChanged { who: T::AccountId, old: Nickname<T>, new: Nickname<T> },
+		/// Owner offered its nickname to another account
+		NicknameTransferProposed { from: T::AccountId, to: T::AccountId, nickname: Nickname<T> },
+		/// Nickname was handed over to another account
+		NicknameTransferred { from: T::AccountId, to: T::AccountId, nickname: Nickname<T> },
+		/// Account rotated its published address
+		AddressUpdated { who: T::AccountId, old: [u8; 32], new: [u8; 32] },
+		/// Account changed the accounts it accepts chat offers from
+		InboundPolicySet { who: T::AccountId, policy: InboundPolicy },
+		/// Room was created with its creator as the only admin
+		RoomCreated { room_id: RoomId, creator: T::AccountId, name: RoomName<T> },
+		/// Admin invited an account to a room
+		MemberInvited { room_id: RoomId, by: T::AccountId, who: T::AccountId },
+		/// Invited account joined a room as a member
+		MemberJoined { room_id: RoomId, who: T::AccountId },
+		/// Member left a room
+		MemberLeft { room_id: RoomId, who: T::AccountId },
+		/// Admin removed a member from a room
+		MemberKicked { room_id: RoomId, by: T::AccountId, who: T::AccountId },
+		/// Admin changed the role of a member
+		RoomRoleChanged { room_id: RoomId, who: T::AccountId, role: RoomRole },
+		/// Last member left a room, so it was removed
+		RoomClosed { room_id: RoomId },
+		/// Account published a signed prekey and added one-time prekeys
+		PrekeysPublished { who: T::AccountId, one_time_prekeys: u32 },
+		/// One-time prekey of `target` was handed out, `None` once they ran out and only the
+		/// signed prekey can be used
+		OneTimePrekeyClaimed { by: T::AccountId, target: T::AccountId, prekey: Option<[u8; 32]> },
+		/// Account is running out of one-time prekeys and should publish more
+		PrekeysLow { who: T::AccountId, remaining: u32 },
+		/// Members of a room changed, so admins have to post a key for the new epoch
+		RoomKeyRotated { room_id: RoomId, epoch: RoomKeyEpoch },
+		/// Admin posted the room key of an epoch
+		RoomKeySet { room_id: RoomId, epoch: RoomKeyEpoch, by: T::AccountId },
+		/// Nickname can no longer be claimed by ordinary users
+		NicknameReserved { nickname: Nickname<T> },
+		/// Reserved nickname can be claimed again
+		NicknameUnreserved { nickname: Nickname<T> },
+		/// Contacts of an account were removed, `complete` tells whether none are left
+		ContactsCleared { who: T::AccountId, removed: u32, complete: bool },
+		/// Account created a contact group
+		ContactGroupCreated { who: T::AccountId, group_id: GroupId },
+		/// Account renamed a contact group
+		ContactGroupRenamed { who: T::AccountId, group_id: GroupId },
+		/// Account removed a contact group
+		ContactGroupRemoved { who: T::AccountId, group_id: GroupId },
+		/// Account added a device with its own messaging key
+		DeviceAdded { who: T::AccountId, device_id: DeviceId },
+		/// Account removed a device
+		DeviceRemoved { who: T::AccountId, device_id: DeviceId },
+		/// Pending nickname transfer was withdrawn before it was accepted
+		NicknameTransferCancelled { from: T::AccountId, to: T::AccountId },
+		/// Admin withdrew the invite of an account to a room
+		InviteRevoked { room_id: RoomId, by: T::AccountId, who: T::AccountId },
+		/// Invited account turned down the invite to a room
+		InviteDeclined { room_id: RoomId, who: T::AccountId },
 	}
 
 	// Errors inform users that something went wrong.
@@ -104,6 +837,122 @@ pub mod pallet {
 		/// AlreadyRegistered - nickname <-> address is already registered
 		AccountIdAlreadyRegistered,
 		NicknameAlreadyRegistered,
+		/// There is no pending offer with the given session id to answer
+		NoPendingOffer,
+		/// Account has no registered nickname
+		NotRegistered,
+		/// There is no nickname transfer to the sender pending
+		NoPendingTransfer,
+		/// Nickname doesn't follow the nickname rules
+		InvalidNickname,
+		/// Nickname is reserved
+		NicknameReserved,
+		/// There is no pending or answered session with the given id between the two accounts
+		NoActiveSession,
+		/// The receiver doesn't accept signals from the sender
+		Blocked,
+		/// The account is not blocked by the sender
+		NotBlocked,
+		/// The receiver doesn't accept chat offers from the sender
+		OfferNotAccepted,
+		/// The sender has no contact with the given address
+		ContactNotFound,
+		/// Another contact of the sender is already linked to the account
+		ContactAccountTaken,
+		/// Sender made too many offers recently, try again later
+		RateLimited,
+		/// There is no room with the given id
+		RoomNotFound,
+		/// Sender is not an admin of the room
+		NotRoomAdmin,
+		/// Account is not a member of the room
+		NotRoomMember,
+		/// Account is already a member of the room
+		AlreadyRoomMember,
+		/// Sender was not invited to the room
+		NotInvited,
+		/// Room has no space for another member or invite
+		RoomFull,
+		/// Room would be left without an admin
+		LastAdmin,
+		/// Address didn't sign the key binding payload of the sender
+		InvalidKeyProof,
+		/// Account did not publish any prekeys
+		NoPrekeys,
+		/// Account would have more one-time prekeys than allowed
+		TooManyPrekeys,
+		/// Room key was encrypted for an epoch which is not the current one
+		StaleRoomKeyEpoch,
+		/// Room key copies don't match the members of the room
+		RoomKeyMembersMismatch,
+		/// Envelope is plain or was sealed with an unknown algorithm
+		UnsupportedEnvelope,
+		/// Contact group does not exist
+		ContactGroupNotFound,
+		/// Account has the maximum number of contact groups
+		TooManyContactGroups,
+		/// Account stores the maximum number of contacts
+		TooManyContacts,
+		/// Device does not exist
+		DeviceNotFound,
+		/// Account has the maximum number of devices
+		TooManyDevices,
+		/// Messaging key is already bound to the account
+		DeviceKeyTaken,
+		/// Signed prekey isn't signed by the messaging key published as the account address
+		InvalidPrekeySignature,
+	}
+
+	#[pallet::hooks]
+	impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+		// expire offers which were not answered in time, oldest first, and forget the feeless usage
+		// of the last era once a new one starts
+		fn on_initialize(n: T::BlockNumber) -> Weight {
+			let (mut head, tail) = <ExpiryQueueBounds<T>>::get();
+			let initial_head = head;
+			let mut expired = 0_u32;
+
+			// offers share the same ttl, so they come due in the order they were made
+			while head != tail && expired < T::MaxExpiriesPerBlock::get() {
+				match <ExpiryQueue<T>>::get(head) {
+					Some((expires_at, _)) if expires_at > n => break,
+					Some((_, key)) => {
+						<ExpiryQueue<T>>::remove(head);
+						Self::expire_session(key);
+					},
+					None => {},
+				}
+				head = head.wrapping_add(1);
+				expired += 1;
+			}
+
+			if head != initial_head {
+				<ExpiryQueueBounds<T>>::put((head, tail));
+			}
+
+			let mut weight = T::WeightInfo::expire_offers(expired);
+
+			let era = Self::feeless_era();
+			let (last_era, calls) = <FeelessCalls<T>>::get();
+			if last_era != era {
+				// every account with an entry made at least one of the calls
+				let _ = <FeelessUsage<T>>::clear_prefix(last_era, calls, None);
+				<FeelessCalls<T>>::put((era, 0));
+				weight.saturating_accrue(T::WeightInfo::clear_feeless_usage(calls));
+			}
+
+			weight
+		}
+
+		// remove expired sessions and rate limits of past windows with the weight left in the
+		// block
+		fn on_idle(n: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let consumed = Self::prune_expired_sessions(remaining_weight);
+			consumed.saturating_add(Self::prune_rate_limits(
+				n,
+				remaining_weight.saturating_sub(consumed),
+			))
+		}
 	}
 
 	// Dispatchable functions allows users to interact with the pallet and invoke state changes.
@@ -113,30 +962,87 @@ pub mod pallet {
 	impl<T: Config> Pallet<T> {
 		// open chat request
 		#[pallet::call_index(0)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
+		#[pallet::weight(T::WeightInfo::offer_chat(offer.len() as u32, welcome_msg.len() as u32))]
 		pub fn offer_chat(
 			origin: OriginFor<T>,
-			welcome_msg: [u8; 300],
-			offer: [u8; 2048],
+			welcome_msg: WelcomeMsg<T>,
+			offer: OfferPayload<T>,
 			to: T::AccountId,
+			room: Option<RoomId>,
+			device: Option<DeviceId>,
 		) -> DispatchResult {
 			// who wanna open discuss
 			let who = ensure_signed(origin)?;
+			Self::ensure_not_blocked(&to, &who)?;
+
+			if let Some(device_id) = device {
+				ensure!(
+					<Devices<T>>::get(&to).iter().any(|device| device.id == device_id),
+					Error::<T>::DeviceNotFound
+				);
+			}
+
+			// members of a room accept offers from each other within it
+			match room {
+				Some(room_id) => {
+					let room = <Rooms<T>>::get(room_id).ok_or(Error::<T>::RoomNotFound)?;
+					ensure!(
+						room.member(&who).is_some() && room.member(&to).is_some(),
+						Error::<T>::NotRoomMember
+					);
+				},
+				None => Self::ensure_accepts_offer(&to, &who)?,
+			}
+			Self::note_offer(&who)?;
+
+			let session_id = <NextSessionId<T>>::mutate(|id| {
+				let current = *id;
+				*id = id.wrapping_add(1);
+				current
+			});
+			let now = <frame_system::Pallet<T>>::block_number();
+
+			<ExpiryQueueBounds<T>>::mutate(|(_, tail)| {
+				let expires_at = now.saturating_add(T::OfferTtl::get());
+				<ExpiryQueue<T>>::insert(
+					*tail,
+					(expires_at, (who.clone(), to.clone(), session_id)),
+				);
+				*tail = tail.wrapping_add(1);
+			});
+
+			<ChatSessions<T>>::insert(
+				(&who, &to, session_id),
+				ChatSession {
+					offer: offer.clone(),
+					welcome_msg: welcome_msg.clone(),
+					state: SessionState::Pending,
+					created_at: now,
+					room,
+					device,
+				},
+			);
+			<PendingOffersTo<T>>::insert(&to, (&who, session_id), ());
+
 			Self::deposit_event(Event::Offer {
 				offer,
 				offered_by: who,
 				offered_to: to,
 				welcome_msg,
+				session_id,
+				room,
+				device,
 			});
 			Ok(())
 		}
 
 		#[pallet::call_index(1)]
-		#[pallet::weight(10_000 + T::DbWeight::get().reads_writes(1, 1).ref_time())]
+		#[pallet::weight(T::WeightInfo::register(nickname.len() as u32))]
 		pub fn register(
 			origin: OriginFor<T>,
-			nickname: [u8; 21],
+			nickname: Nickname<T>,
 			address: [u8; 32],
+			key_proof: [u8; 64],
 		) -> DispatchResult {
 			let owner = ensure_signed(origin)?;
 
@@ -144,52 +1050,1402 @@ pub mod pallet {
 				return Err(Error::<T>::AccountIdAlreadyRegistered.into())
 			}
 
-			if <ItemByNicknameStore<T>>::contains_key(nickname.clone()) {
-				return Err(Error::<T>::NicknameAlreadyRegistered.into())
-			}
+			let nickname = Self::claimable_nickname(&nickname)?;
+			Self::bind_key(&owner, &address, &key_proof)?;
 
-			<ItemByNicknameStore<T>>::insert(nickname, owner.clone());
-			<ItemByAccountIdStore<T>>::insert(owner, ItemByAccountId { address, nickname });
+			let deposit = Self::deposit_for(nickname.len().saturating_add(address.len()));
+			T::Currency::reserve(&owner, deposit)?;
+
+			<ItemByNicknameStore<T>>::insert(&nickname, owner.clone());
+			<ItemByAccountIdStore<T>>::insert(
+				owner,
+				ItemByAccountId { address, nickname, deposit },
+			);
 
 			Ok(())
 		}
 		// answering on open chat request
 		#[pallet::call_index(2)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
+		#[pallet::weight(T::WeightInfo::answer_chat(answer.len() as u32))]
 		pub fn answer_chat(
 			origin: OriginFor<T>,
-			answer: [u8; 2048],
+			answer: AnswerPayload<T>,
 			to: T::AccountId,
+			session_id: SessionId,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			Self::deposit_event(Event::Answer { answer, answer_from: who, answer_to: to });
+			Self::ensure_not_blocked(&to, &who)?;
+
+			// the offer was made by `to` to the sender
+			Self::settle_pending_session(&to, &who, session_id, SessionState::Answered)?;
+
+			Self::deposit_event(Event::Answer {
+				answer,
+				answer_from: who,
+				answer_to: to,
+				session_id,
+			});
 			Ok(())
 		}
 		// updating or inserting contact to sender contact list
 		#[pallet::call_index(3)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
+		#[pallet::weight(T::WeightInfo::upsert_contact(
+			contact_name.ciphertext.len() as u32,
+			contact_addr.len() as u32,
+		))]
 		pub fn upsert_contact(
 			origin: OriginFor<T>,
-			contact_name: EncodedContactName,
-			contact_addr: EncodedContactAddr,
+			contact_name: ContactEnvelope<T>,
+			contact_addr: EncodedContactAddr<T>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			<ContactByAccountIdStore<T>>::set(
-				who,
-				contact_addr,
-				ContactByAccountId { name: contact_name },
-			);
-			Ok(())
+			Self::do_upsert_contact(&who, contact_name, contact_addr)
 		}
 
 		#[pallet::call_index(4)]
-		#[pallet::weight(10_000 + T::DbWeight::get().writes(1).ref_time())]
+		#[pallet::weight(T::WeightInfo::remove_contact(contact_addr.len() as u32))]
 		pub fn remove_contact(
 			origin: OriginFor<T>,
-			contact_addr: EncodedContactAddr,
+			contact_addr: EncodedContactAddr<T>,
 		) -> DispatchResult {
 			let who = ensure_signed(origin)?;
-			<ContactByAccountIdStore<T>>::remove(who, contact_addr);
+			Self::do_remove_contact(&who, &contact_addr);
+			Ok(())
+		}
+
+		// releasing the sender nickname
+		#[pallet::call_index(5)]
+		#[pallet::weight(T::WeightInfo::unregister())]
+		pub fn unregister(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let item = Self::do_unregister(&who)?;
+			Self::deposit_event(Event::Unregistered { who, nickname: item.nickname });
+			Ok(())
+		}
+
+		#[pallet::call_index(6)]
+		#[pallet::weight(T::WeightInfo::change_nickname(nickname.len() as u32))]
+		pub fn change_nickname(origin: OriginFor<T>, nickname: Nickname<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let mut item =
+				<ItemByAccountIdStore<T>>::try_get(&who).map_err(|_| Error::<T>::NotRegistered)?;
+
+			let nickname = Self::claimable_nickname(&nickname)?;
+
+			let deposit = Self::deposit_for(nickname.len().saturating_add(item.address.len()));
+			Self::adjust_deposit(&who, item.deposit, deposit)?;
+
+			let old = sp_std::mem::replace(&mut item.nickname, nickname.clone());
+			item.deposit = deposit;
+
+			<ItemByNicknameStore<T>>::remove(&old);
+			<ItemByNicknameStore<T>>::insert(&nickname, who.clone());
+			<ItemByAccountIdStore<T>>::insert(&who, item);
+
+			// the pending transfer was proposed for the old nickname
+			if let Some(to) = <PendingNicknameTransfers<T>>::take(&who) {
+				Self::deposit_event(Event::NicknameTransferCancelled { from: who.clone(), to });
+			}
+
+			Self::deposit_event(Event::NicknameChanged { who, old, new: nickname });
+			Ok(())
+		}
+
+		// offering the sender nickname to another account, which has to accept it
+		#[pallet::call_index(7)]
+		#[pallet::weight(T::WeightInfo::transfer_nickname())]
+		pub fn transfer_nickname(origin: OriginFor<T>, to: T::AccountId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let item =
+				<ItemByAccountIdStore<T>>::try_get(&who).map_err(|_| Error::<T>::NotRegistered)?;
+
+			<PendingNicknameTransfers<T>>::insert(&who, &to);
+
+			Self::deposit_event(Event::NicknameTransferProposed {
+				from: who,
+				to,
+				nickname: item.nickname,
+			});
+			Ok(())
+		}
+
+		#[pallet::call_index(8)]
+		#[pallet::weight(T::WeightInfo::accept_nickname())]
+		pub fn accept_nickname(
+			origin: OriginFor<T>,
+			from: T::AccountId,
+			address: [u8; 32],
+			key_proof: [u8; 64],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			if <PendingNicknameTransfers<T>>::get(&from).as_ref() != Some(&who) {
+				return Err(Error::<T>::NoPendingTransfer.into())
+			}
+
+			if <ItemByAccountIdStore<T>>::contains_key(&who) {
+				return Err(Error::<T>::AccountIdAlreadyRegistered.into())
+			}
+			Self::bind_key(&who, &address, &key_proof)?;
+
+			let ItemByAccountId { nickname, .. } = Self::do_unregister(&from)?;
+
+			let deposit = Self::deposit_for(nickname.len().saturating_add(address.len()));
+			T::Currency::reserve(&who, deposit)?;
+
+			<ItemByNicknameStore<T>>::insert(&nickname, who.clone());
+			<ItemByAccountIdStore<T>>::insert(
+				&who,
+				ItemByAccountId { address, nickname: nickname.clone(), deposit },
+			);
+
+			Self::deposit_event(Event::NicknameTransferred { from, to: who, nickname });
+			Ok(())
+		}
+
+		// rotating the published address, the previous one is kept in the history
+		#[pallet::call_index(9)]
+		#[pallet::weight(T::WeightInfo::update_address())]
+		pub fn update_address(
+			origin: OriginFor<T>,
+			address: [u8; 32],
+			key_proof: [u8; 64],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			ensure!(<ItemByAccountIdStore<T>>::contains_key(&who), Error::<T>::NotRegistered);
+			ensure!(
+				<Devices<T>>::get(&who).iter().all(|device| device.public_key != address),
+				Error::<T>::DeviceKeyTaken
+			);
+			Self::bind_key(&who, &address, &key_proof)?;
+
+			let old = <ItemByAccountIdStore<T>>::mutate(&who, |item| {
+				sp_std::mem::replace(&mut item.address, address)
+			});
+			// prekeys were signed by the old identity key
+			Self::remove_prekeys(&who);
+
+			let max_history = T::MaxAddressHistory::get() as usize;
+			if max_history > 0 {
+				let now = <frame_system::Pallet<T>>::block_number();
+				<AddressHistory<T>>::mutate(&who, |history| {
+					// drop the oldest address to make room for the new one
+					if history.len() >= max_history {
+						history.remove(0);
+					}
+					let _ = history.try_push((old, now));
+				});
+			}
+
+			Self::deposit_event(Event::AddressUpdated { who, old, new: address });
+			Ok(())
+		}
+
+		#[pallet::call_index(10)]
+		#[pallet::weight(T::WeightInfo::reserve_nickname(nickname.len() as u32))]
+		pub fn reserve_nickname(origin: OriginFor<T>, nickname: Nickname<T>) -> DispatchResult {
+			T::ReservedNicknameOrigin::ensure_origin(origin)?;
+
+			let nickname = Self::normalize_nickname(&nickname)?;
+			<ReservedNicknames<T>>::insert(&nickname, ());
+
+			Self::deposit_event(Event::NicknameReserved { nickname });
+			Ok(())
+		}
+
+		#[pallet::call_index(11)]
+		#[pallet::weight(T::WeightInfo::unreserve_nickname(nickname.len() as u32))]
+		pub fn unreserve_nickname(origin: OriginFor<T>, nickname: Nickname<T>) -> DispatchResult {
+			T::ReservedNicknameOrigin::ensure_origin(origin)?;
+
+			let nickname = Self::normalize_nickname(&nickname)?;
+			<ReservedNicknames<T>>::remove(&nickname);
+
+			Self::deposit_event(Event::NicknameUnreserved { nickname });
+			Ok(())
+		}
+
+		// trickle ICE candidates to the other side of a session until it expires
+		#[pallet::call_index(12)]
+		#[pallet::weight(T::WeightInfo::add_ice_candidates(candidates.len() as u32))]
+		pub fn add_ice_candidates(
+			origin: OriginFor<T>,
+			peer: T::AccountId,
+			session_id: SessionId,
+			candidates: IceCandidatesPayload<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			Self::ensure_not_blocked(&peer, &who)?;
+
+			// the sender may be either the offerer or the offeree of the session
+			let session = <ChatSessions<T>>::get((&who, &peer, session_id))
+				.or_else(|| <ChatSessions<T>>::get((&peer, &who, session_id)))
+				.ok_or(Error::<T>::NoActiveSession)?;
+			ensure!(
+				matches!(session.state, SessionState::Pending | SessionState::Answered),
+				Error::<T>::NoActiveSession
+			);
+
+			Self::deposit_event(Event::IceCandidates {
+				from: who,
+				to: peer,
+				session_id,
+				candidates,
+			});
+			Ok(())
+		}
+
+		// decline a pending offer, the rejected session is kept until it would have expired
+		#[pallet::call_index(13)]
+		#[pallet::weight(T::WeightInfo::reject_chat())]
+		pub fn reject_chat(
+			origin: OriginFor<T>,
+			from: T::AccountId,
+			session_id: SessionId,
+			reason_code: u8,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::settle_pending_session(&from, &who, session_id, SessionState::Rejected)?;
+
+			Self::deposit_event(Event::OfferRejected {
+				offered_by: from,
+				offered_to: who,
+				session_id,
+				reason_code,
+			});
+			Ok(())
+		}
+
+		// withdraw an offer which wasn't answered yet
+		#[pallet::call_index(14)]
+		#[pallet::weight(T::WeightInfo::cancel_offer())]
+		pub fn cancel_offer(
+			origin: OriginFor<T>,
+			to: T::AccountId,
+			session_id: SessionId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			Self::take_pending_session(&who, &to, session_id)?;
+
+			Self::deposit_event(Event::OfferCancelled {
+				offered_by: who,
+				offered_to: to,
+				session_id,
+			});
+			Ok(())
+		}
+
+		// stop accepting signals from an account, no event is emitted to keep the list private
+		#[pallet::call_index(15)]
+		#[pallet::weight(T::WeightInfo::block_account())]
+		pub fn block_account(origin: OriginFor<T>, account: T::AccountId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			if <BlockedAccounts<T>>::contains_key(&who, &account) {
+				return Ok(())
+			}
+
+			let deposit = Self::deposit_for(account.encoded_size());
+			T::Currency::reserve(&who, deposit)?;
+			<BlockedAccounts<T>>::insert(&who, &account, deposit);
+
+			Ok(())
+		}
+
+		// accept signals from a previously blocked account again
+		#[pallet::call_index(16)]
+		#[pallet::weight(T::WeightInfo::unblock_account())]
+		pub fn unblock_account(origin: OriginFor<T>, account: T::AccountId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let deposit =
+				<BlockedAccounts<T>>::take(&who, &account).ok_or(Error::<T>::NotBlocked)?;
+			T::Currency::unreserve(&who, deposit);
+
+			Ok(())
+		}
+
+		// choose which accounts can send chat offers to the sender
+		#[pallet::call_index(17)]
+		#[pallet::weight(T::WeightInfo::set_inbound_policy())]
+		pub fn set_inbound_policy(origin: OriginFor<T>, policy: InboundPolicy) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			if policy == InboundPolicy::default() {
+				<InboundPolicies<T>>::remove(&who);
+			} else {
+				<InboundPolicies<T>>::insert(&who, policy);
+			}
+
+			Self::deposit_event(Event::InboundPolicySet { who, policy });
+			Ok(())
+		}
+
+		// link a contact to the account behind it, or unlink it with `None`
+		#[pallet::call_index(18)]
+		#[pallet::weight(T::WeightInfo::set_contact_account(contact_addr.len() as u32))]
+		pub fn set_contact_account(
+			origin: OriginFor<T>,
+			contact_addr: EncodedContactAddr<T>,
+			account: Option<T::AccountId>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let mut contact = <ContactByAccountIdStore<T>>::try_get(&who, &contact_addr)
+				.map_err(|_| Error::<T>::ContactNotFound)?;
+
+			if let Some(account) = &account {
+				ensure!(
+					<ContactIndex<T>>::get(&who, account).map_or(true, |addr| addr == contact_addr),
+					Error::<T>::ContactAccountTaken
+				);
+			}
+
+			let old = sp_std::mem::replace(&mut contact.account, account);
+			let deposit = Self::contact_deposit(&contact_addr, &contact);
+			Self::adjust_deposit(&who, contact.deposit, deposit)?;
+
+			if let Some(old) = &old {
+				<ContactIndex<T>>::remove(&who, old);
+			}
+			if let Some(new) = &contact.account {
+				<ContactIndex<T>>::insert(&who, new, &contact_addr);
+			}
+
+			contact.deposit = deposit;
+			<ContactByAccountIdStore<T>>::insert(&who, &contact_addr, contact);
+
+			Ok(())
+		}
+
+		// open a group chat room with the sender as its admin
+		#[pallet::call_index(19)]
+		#[pallet::weight(T::WeightInfo::create_room(name.len() as u32))]
+		pub fn create_room(origin: OriginFor<T>, name: RoomName<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let deposit = Self::deposit_for(name.len());
+			T::Currency::reserve(&who, deposit)?;
+			let admin = Self::new_room_member(&who, RoomRole::Admin)?;
+
+			let room_id = <NextRoomId<T>>::mutate(|id| {
+				let current = *id;
+				*id = id.wrapping_add(1);
+				current
+			});
+			let members =
+				BoundedVec::try_from(sp_std::vec![admin]).map_err(|_| Error::<T>::RoomFull)?;
+
+			<Rooms<T>>::insert(
+				room_id,
+				Room {
+					name: name.clone(),
+					creator: who.clone(),
+					members,
+					invited: Default::default(),
+					deposit,
+				},
+			);
+
+			Self::deposit_event(Event::RoomCreated { room_id, creator: who, name });
+			Ok(())
+		}
+
+		#[pallet::call_index(20)]
+		#[pallet::weight(T::WeightInfo::invite())]
+		pub fn invite(
+			origin: OriginFor<T>,
+			room_id: RoomId,
+			account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let invited = <Rooms<T>>::try_mutate(room_id, |room| -> Result<bool, DispatchError> {
+				let room = room.as_mut().ok_or(Error::<T>::RoomNotFound)?;
+				ensure!(room.is_admin(&who), Error::<T>::NotRoomAdmin);
+				ensure!(room.member(&account).is_none(), Error::<T>::AlreadyRoomMember);
+
+				if room.invited.contains(&account) {
+					return Ok(false)
+				}
+				room.invited.try_push(account.clone()).map_err(|_| Error::<T>::RoomFull)?;
+				Ok(true)
+			})?;
+
+			// the account was invited before
+			if !invited {
+				return Ok(())
+			}
+
+			Self::deposit_event(Event::MemberInvited { room_id, by: who, who: account });
+			Ok(())
+		}
+
+		#[pallet::call_index(21)]
+		#[pallet::weight(T::WeightInfo::join())]
+		pub fn join(origin: OriginFor<T>, room_id: RoomId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			<Rooms<T>>::try_mutate(room_id, |room| -> DispatchResult {
+				let room = room.as_mut().ok_or(Error::<T>::RoomNotFound)?;
+
+				let invite = room
+					.invited
+					.iter()
+					.position(|invited| invited == &who)
+					.ok_or(Error::<T>::NotInvited)?;
+				ensure!(
+					room.members.len() < T::MaxRoomMembers::get() as usize,
+					Error::<T>::RoomFull
+				);
+				room.invited.remove(invite);
+
+				let member = Self::new_room_member(&who, RoomRole::Member)?;
+				room.members.try_push(member).map_err(|_| Error::<T>::RoomFull)?;
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::MemberJoined { room_id, who });
+			Self::membership_changed(room_id, false);
+			Ok(())
+		}
+
+		#[pallet::call_index(22)]
+		#[pallet::weight(T::WeightInfo::leave())]
+		pub fn leave(origin: OriginFor<T>, room_id: RoomId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let room = <Rooms<T>>::get(room_id).ok_or(Error::<T>::RoomNotFound)?;
+			let closed = Self::remove_room_member(room_id, room, &who)?;
+
+			Self::deposit_event(Event::MemberLeft { room_id, who });
+			Self::membership_changed(room_id, closed);
+			Ok(())
+		}
+
+		#[pallet::call_index(23)]
+		#[pallet::weight(T::WeightInfo::kick())]
+		pub fn kick(
+			origin: OriginFor<T>,
+			room_id: RoomId,
+			account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let room = <Rooms<T>>::get(room_id).ok_or(Error::<T>::RoomNotFound)?;
+			ensure!(room.is_admin(&who), Error::<T>::NotRoomAdmin);
+			let closed = Self::remove_room_member(room_id, room, &account)?;
+
+			Self::deposit_event(Event::MemberKicked { room_id, by: who, who: account });
+			Self::membership_changed(room_id, closed);
+			Ok(())
+		}
+
+		// promote a member to an admin or demote an admin to a member
+		#[pallet::call_index(24)]
+		#[pallet::weight(T::WeightInfo::set_admin())]
+		pub fn set_admin(
+			origin: OriginFor<T>,
+			room_id: RoomId,
+			account: T::AccountId,
+			admin: bool,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			let role = if admin { RoomRole::Admin } else { RoomRole::Member };
+
+			<Rooms<T>>::try_mutate(room_id, |room| -> DispatchResult {
+				let room = room.as_mut().ok_or(Error::<T>::RoomNotFound)?;
+				ensure!(room.is_admin(&who), Error::<T>::NotRoomAdmin);
+
+				let member = room
+					.members
+					.iter_mut()
+					.find(|member| member.account == account)
+					.ok_or(Error::<T>::NotRoomMember)?;
+				member.role = role;
+
+				ensure!(room.has_admin(), Error::<T>::LastAdmin);
+				Ok(())
+			})?;
+
+			Self::deposit_event(Event::RoomRoleChanged { room_id, who: account, role });
+			Ok(())
+		}
+
+		// post the room key of the current epoch, encrypted to each member
+		#[pallet::call_index(25)]
+		#[pallet::weight(T::WeightInfo::set_room_key(keys.len() as u32))]
+		pub fn set_room_key(
+			origin: OriginFor<T>,
+			room_id: RoomId,
+			epoch: RoomKeyEpoch,
+			keys: RoomKeyCopies<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let room = <Rooms<T>>::get(room_id).ok_or(Error::<T>::RoomNotFound)?;
+			ensure!(room.is_admin(&who), Error::<T>::NotRoomAdmin);
+			// the members may have changed since the admin encrypted the key
+			ensure!(<RoomKeyEpochs<T>>::get(room_id) == epoch, Error::<T>::StaleRoomKeyEpoch);
+			ensure!(
+				keys.len() == room.members.len() &&
+					room.members.iter().all(|member| keys.contains_key(&member.account)),
+				Error::<T>::RoomKeyMembersMismatch
+			);
+
+			let deposit = Self::deposit_for(keys.encoded_size());
+			T::Currency::reserve(&who, deposit)?;
+			if let Some(old) = <RoomKeys<T>>::take(room_id) {
+				T::Currency::unreserve(&old.posted_by, old.deposit);
+			}
+			<RoomKeys<T>>::insert(room_id, RoomKey { posted_by: who.clone(), keys, deposit });
+
+			Self::deposit_event(Event::RoomKeySet { room_id, epoch, by: who });
+			Ok(())
+		}
+
+		// replace the signed prekey and add one-time prekeys to the ones not claimed yet
+		#[pallet::call_index(26)]
+		#[pallet::weight(T::WeightInfo::publish_prekeys(one_time_prekeys.len() as u32))]
+		pub fn publish_prekeys(
+			origin: OriginFor<T>,
+			signed_prekey: SignedPrekey,
+			one_time_prekeys: OneTimePrekeys<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let item =
+				<ItemByAccountIdStore<T>>::try_get(&who).map_err(|_| Error::<T>::NotRegistered)?;
+			let valid = sp_io::crypto::ed25519_verify(
+				&ed25519::Signature::from_raw(signed_prekey.signature),
+				&signed_prekey.key,
+				&ed25519::Public::from_raw(item.address),
+			);
+			ensure!(valid, Error::<T>::InvalidPrekeySignature);
+
+			let mut bundle = <PrekeyBundles<T>>::get(&who).unwrap_or_else(|| PrekeyBundle {
+				signed_prekey: signed_prekey.clone(),
+				one_time_prekeys: Default::default(),
+				deposit: Zero::zero(),
+			});
+			bundle.signed_prekey = signed_prekey;
+			for prekey in one_time_prekeys {
+				bundle
+					.one_time_prekeys
+					.try_push(prekey)
+					.map_err(|_| Error::<T>::TooManyPrekeys)?;
+			}
+
+			let deposit = Self::prekeys_deposit(&bundle);
+			Self::adjust_deposit(&who, bundle.deposit, deposit)?;
+			bundle.deposit = deposit;
+
+			let one_time_prekeys = bundle.one_time_prekeys.len() as u32;
+			<PrekeyBundles<T>>::insert(&who, bundle);
+
+			Self::deposit_event(Event::PrekeysPublished { who, one_time_prekeys });
+			Ok(())
+		}
+
+		// hand out the oldest one-time prekey of `target`, which can't be claimed again
+		#[pallet::call_index(27)]
+		#[pallet::weight(T::WeightInfo::claim_one_time_prekey())]
+		pub fn claim_one_time_prekey(origin: OriginFor<T>, target: T::AccountId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let mut bundle = <PrekeyBundles<T>>::get(&target).ok_or(Error::<T>::NoPrekeys)?;
+			let prekey = if bundle.one_time_prekeys.is_empty() {
+				None
+			} else {
+				let prekey = bundle.one_time_prekeys.remove(0);
+				let deposit = Self::prekeys_deposit(&bundle);
+				Self::adjust_deposit(&target, bundle.deposit, deposit)?;
+				bundle.deposit = deposit;
+				Some(prekey)
+			};
+
+			let remaining = bundle.one_time_prekeys.len() as u32;
+			<PrekeyBundles<T>>::insert(&target, bundle);
+
+			Self::deposit_event(Event::OneTimePrekeyClaimed {
+				by: who,
+				target: target.clone(),
+				prekey,
+			});
+			if remaining < T::MinOneTimePrekeys::get() {
+				Self::deposit_event(Event::PrekeysLow { who: target, remaining });
+			}
+			Ok(())
+		}
+
+		// upserting several contacts at once, e.g. when syncing a new device
+		#[pallet::call_index(28)]
+		#[pallet::weight(T::WeightInfo::batch_upsert_contacts(contacts.len() as u32))]
+		pub fn batch_upsert_contacts(
+			origin: OriginFor<T>,
+			contacts: ContactBatch<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			for (contact_name, contact_addr) in contacts {
+				Self::do_upsert_contact(&who, contact_name, contact_addr)?;
+			}
+			Ok(())
+		}
+
+		#[pallet::call_index(29)]
+		#[pallet::weight(T::WeightInfo::batch_remove_contacts(contact_addrs.len() as u32))]
+		pub fn batch_remove_contacts(
+			origin: OriginFor<T>,
+			contact_addrs: ContactAddrBatch<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			for contact_addr in contact_addrs {
+				Self::do_remove_contact(&who, &contact_addr);
+			}
+			Ok(())
+		}
+
+		// removing up to `limit` contacts of the sender, but no more than `MaxContactBatch`
+		//
+		// Removed entries are gone from the prefix, so the next call continues where this one
+		// stopped and large lists can be wiped across several blocks.
+		#[pallet::call_index(30)]
+		#[pallet::weight(T::WeightInfo::clear_contacts((*limit).min(T::MaxContactBatch::get())))]
+		pub fn clear_contacts(origin: OriginFor<T>, limit: u32) -> DispatchResultWithPostInfo {
+			let who = ensure_signed(origin)?;
+			let limit = limit.min(T::MaxContactBatch::get());
+
+			let mut removed = 0_u32;
+			for (_, contact) in
+				<ContactByAccountIdStore<T>>::drain_prefix(&who).take(limit as usize)
+			{
+				Self::release_contact(&who, contact);
+				removed += 1;
+			}
+			let complete = <ContactByAccountIdStore<T>>::iter_key_prefix(&who).next().is_none();
+
+			Self::deposit_event(Event::ContactsCleared { who, removed, complete });
+			Ok(Some(T::WeightInfo::clear_contacts(removed)).into())
+		}
+
+		#[pallet::call_index(31)]
+		#[pallet::weight(T::WeightInfo::create_contact_group())]
+		pub fn create_contact_group(
+			origin: OriginFor<T>,
+			name: ContactGroupName<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(name.is_supported(), Error::<T>::UnsupportedEnvelope);
+
+			let mut groups = <ContactGroupsOf<T>>::get(&who);
+			let group_id = groups.next_id;
+			groups
+				.groups
+				.try_insert(group_id, name)
+				.map_err(|_| Error::<T>::TooManyContactGroups)?;
+			groups.next_id = group_id.saturating_add(1);
+			Self::update_contact_groups(&who, groups)?;
+
+			Self::deposit_event(Event::ContactGroupCreated { who, group_id });
+			Ok(())
+		}
+
+		#[pallet::call_index(32)]
+		#[pallet::weight(T::WeightInfo::rename_contact_group())]
+		pub fn rename_contact_group(
+			origin: OriginFor<T>,
+			group_id: GroupId,
+			name: ContactGroupName<T>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(name.is_supported(), Error::<T>::UnsupportedEnvelope);
+
+			let mut groups = <ContactGroupsOf<T>>::get(&who);
+			*groups.groups.get_mut(&group_id).ok_or(Error::<T>::ContactGroupNotFound)? = name;
+			Self::update_contact_groups(&who, groups)?;
+
+			Self::deposit_event(Event::ContactGroupRenamed { who, group_id });
+			Ok(())
+		}
+
+		// contacts keep the id of a removed group until their metadata is set again, clients
+		// skip ids they don't know
+		#[pallet::call_index(33)]
+		#[pallet::weight(T::WeightInfo::remove_contact_group())]
+		pub fn remove_contact_group(origin: OriginFor<T>, group_id: GroupId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let mut groups = <ContactGroupsOf<T>>::get(&who);
+			groups.groups.remove(&group_id).ok_or(Error::<T>::ContactGroupNotFound)?;
+			Self::update_contact_groups(&who, groups)?;
+
+			Self::deposit_event(Event::ContactGroupRemoved { who, group_id });
+			Ok(())
+		}
+
+		// replace the groups, flags and note of a contact
+		#[pallet::call_index(34)]
+		#[pallet::weight(T::WeightInfo::set_contact_metadata(contact_addr.len() as u32))]
+		pub fn set_contact_metadata(
+			origin: OriginFor<T>,
+			contact_addr: EncodedContactAddr<T>,
+			groups: BoundedVec<GroupId, T::MaxContactGroups>,
+			flags: ContactFlags,
+			note: Option<ContactNote<T>>,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+			ensure!(
+				note.as_ref().map_or(true, |note| note.is_supported()),
+				Error::<T>::UnsupportedEnvelope
+			);
+
+			let mut contact = <ContactByAccountIdStore<T>>::try_get(&who, &contact_addr)
+				.map_err(|_| Error::<T>::ContactNotFound)?;
+
+			let known = <ContactGroupsOf<T>>::get(&who).groups;
+			ensure!(
+				groups.iter().all(|group_id| known.contains_key(group_id)),
+				Error::<T>::ContactGroupNotFound
+			);
+			let mut groups = groups.into_inner();
+			groups.sort();
+			groups.dedup();
+
+			contact.groups = groups.try_into().expect("deduplicating doesn't add groups; qed");
+			contact.flags = flags;
+			contact.note = note;
+			let deposit = Self::contact_deposit(&contact_addr, &contact);
+			Self::adjust_deposit(&who, contact.deposit, deposit)?;
+			contact.deposit = deposit;
+			<ContactByAccountIdStore<T>>::insert(&who, &contact_addr, contact);
+
+			Ok(())
+		}
+
+		// bind another messaging key to the sender, used by one of its devices
+		#[pallet::call_index(35)]
+		#[pallet::weight(T::WeightInfo::add_device(label.len() as u32))]
+		pub fn add_device(
+			origin: OriginFor<T>,
+			public_key: [u8; 32],
+			label: DeviceLabel<T>,
+			key_proof: [u8; 64],
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let item =
+				<ItemByAccountIdStore<T>>::try_get(&who).map_err(|_| Error::<T>::NotRegistered)?;
+			let mut devices = <Devices<T>>::get(&who);
+			ensure!(
+				item.address != public_key &&
+					devices.iter().all(|device| device.public_key != public_key),
+				Error::<T>::DeviceKeyTaken
+			);
+			Self::bind_key(&who, &public_key, &key_proof)?;
+
+			let deposit = Self::deposit_for(label.len().saturating_add(public_key.len()));
+			let device_id = <NextDeviceId<T>>::get(&who);
+			devices
+				.try_push(Device {
+					id: device_id,
+					public_key,
+					label,
+					added_at: <frame_system::Pallet<T>>::block_number(),
+					deposit,
+				})
+				.map_err(|_| Error::<T>::TooManyDevices)?;
+			T::Currency::reserve(&who, deposit)?;
+
+			<NextDeviceId<T>>::insert(&who, device_id.wrapping_add(1));
+			<Devices<T>>::insert(&who, devices);
+
+			Self::deposit_event(Event::DeviceAdded { who, device_id });
+			Ok(())
+		}
+
+		#[pallet::call_index(36)]
+		#[pallet::weight(T::WeightInfo::remove_device())]
+		pub fn remove_device(origin: OriginFor<T>, device_id: DeviceId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let mut devices = <Devices<T>>::get(&who);
+			let position = devices
+				.iter()
+				.position(|device| device.id == device_id)
+				.ok_or(Error::<T>::DeviceNotFound)?;
+			let device = devices.remove(position);
+			T::Currency::unreserve(&who, device.deposit);
+
+			if devices.is_empty() {
+				<Devices<T>>::remove(&who);
+			} else {
+				<Devices<T>>::insert(&who, devices);
+			}
+
+			Self::deposit_event(Event::DeviceRemoved { who, device_id });
+			Ok(())
+		}
+
+		// withdraw a nickname transfer which wasn't accepted yet
+		#[pallet::call_index(37)]
+		#[pallet::weight(T::WeightInfo::cancel_nickname_transfer())]
+		pub fn cancel_nickname_transfer(origin: OriginFor<T>) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			let to =
+				<PendingNicknameTransfers<T>>::take(&who).ok_or(Error::<T>::NoPendingTransfer)?;
+
+			Self::deposit_event(Event::NicknameTransferCancelled { from: who, to });
+			Ok(())
+		}
+
+		// withdraw an invite to a room which wasn't taken up yet
+		#[pallet::call_index(38)]
+		#[pallet::weight(T::WeightInfo::revoke_invite())]
+		pub fn revoke_invite(
+			origin: OriginFor<T>,
+			room_id: RoomId,
+			account: T::AccountId,
+		) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			<Rooms<T>>::try_mutate(room_id, |room| -> DispatchResult {
+				let room = room.as_mut().ok_or(Error::<T>::RoomNotFound)?;
+				ensure!(room.is_admin(&who), Error::<T>::NotRoomAdmin);
+				Self::remove_invite(room, &account)
+			})?;
+
+			Self::deposit_event(Event::InviteRevoked { room_id, by: who, who: account });
+			Ok(())
+		}
+
+		// turn down an invite to a room
+		#[pallet::call_index(39)]
+		#[pallet::weight(T::WeightInfo::decline_invite())]
+		pub fn decline_invite(origin: OriginFor<T>, room_id: RoomId) -> DispatchResult {
+			let who = ensure_signed(origin)?;
+
+			<Rooms<T>>::try_mutate(room_id, |room| -> DispatchResult {
+				let room = room.as_mut().ok_or(Error::<T>::RoomNotFound)?;
+				Self::remove_invite(room, &who)
+			})?;
+
+			Self::deposit_event(Event::InviteDeclined { room_id, who });
+			Ok(())
+		}
+	}
+
+	impl<T: Config> Pallet<T> {
+		/// Normalizes `nickname` according to `T::NicknameValidator`.
+		pub fn normalize_nickname(nickname: &[u8]) -> Result<Nickname<T>, DispatchError> {
+			T::NicknameValidator::validate(nickname)
+				.and_then(|normalized| Nickname::<T>::try_from(normalized).ok())
+				.ok_or_else(|| Error::<T>::InvalidNickname.into())
+		}
+
+		// normalizes a nickname an ordinary user wants to claim and checks it is free
+		fn claimable_nickname(nickname: &[u8]) -> Result<Nickname<T>, DispatchError> {
+			let nickname = Self::normalize_nickname(nickname)?;
+
+			ensure!(!<ReservedNicknames<T>>::contains_key(&nickname), Error::<T>::NicknameReserved);
+			ensure!(
+				!<ItemByNicknameStore<T>>::contains_key(&nickname),
+				Error::<T>::NicknameAlreadyRegistered
+			);
+
+			Ok(nickname)
+		}
+
+		// fails if `target` has blocked `sender`
+		fn ensure_not_blocked(target: &T::AccountId, sender: &T::AccountId) -> DispatchResult {
+			ensure!(!<BlockedAccounts<T>>::contains_key(target, sender), Error::<T>::Blocked);
+			Ok(())
+		}
+
+		// fails if the inbound policy of `to` doesn't allow offers from `from`
+		fn ensure_accepts_offer(to: &T::AccountId, from: &T::AccountId) -> DispatchResult {
+			let accepted = match <InboundPolicies<T>>::get(to) {
+				InboundPolicy::Everyone => true,
+				InboundPolicy::ContactsOnly => <ContactIndex<T>>::contains_key(to, from),
+				InboundPolicy::Nobody => false,
+			};
+			ensure!(accepted, Error::<T>::OfferNotAccepted);
+			Ok(())
+		}
+
+		/// Whether `who` already made `MaxOffersPerWindow` offers in its current window.
+		pub fn is_offer_rate_limited(who: &T::AccountId) -> bool {
+			let (start, count) = <OfferRateLimits<T>>::get(who);
+			let now = <frame_system::Pallet<T>>::block_number();
+
+			now < start.saturating_add(T::OfferRateWindow::get()) &&
+				count >= T::MaxOffersPerWindow::get()
+		}
+
+		// counts an offer of `who` against its rate limit, starting a new window if there is none
+		// or the last one is over
+		fn note_offer(who: &T::AccountId) -> DispatchResult {
+			let now = <frame_system::Pallet<T>>::block_number();
+			let window = T::OfferRateWindow::get();
+
+			let started = <OfferRateLimits<T>>::try_mutate_exists(
+				who,
+				|limit| -> Result<bool, DispatchError> {
+					let (start, count, started) = match *limit {
+						Some((start, count)) if now < start.saturating_add(window) =>
+							(start, count, false),
+						_ => (now, 0, true),
+					};
+					ensure!(count < T::MaxOffersPerWindow::get(), Error::<T>::RateLimited);
+					*limit = Some((start, count.saturating_add(1)));
+					Ok(started)
+				},
+			)?;
+
+			// windows share the same length, so they end in the order they were started
+			if started {
+				<RateLimitQueueBounds<T>>::mutate(|(_, tail)| {
+					<RateLimitQueue<T>>::insert(*tail, (now.saturating_add(window), who.clone()));
+					*tail = tail.wrapping_add(1);
+				});
+			}
+			Ok(())
+		}
+
+		// removes sessions queued by `expire_session` within `remaining_weight`
+		fn prune_expired_sessions(remaining_weight: Weight) -> Weight {
+			let mut consumed = T::WeightInfo::prune_sessions(0);
+			let per_session = T::WeightInfo::prune_sessions(1).saturating_sub(consumed);
+
+			if remaining_weight.any_lt(consumed) {
+				return Weight::zero()
+			}
+
+			let (mut head, tail) = <PruneQueueBounds<T>>::get();
+			let initial_head = head;
+
+			while head != tail && consumed.saturating_add(per_session).all_lte(remaining_weight) {
+				if let Some(key) = <PruneQueue<T>>::take(head) {
+					<ChatSessions<T>>::remove(key);
+				}
+				head = head.wrapping_add(1);
+				consumed.saturating_accrue(per_session);
+			}
+
+			if head != initial_head {
+				<PruneQueueBounds<T>>::put((head, tail));
+			}
+
+			consumed
+		}
+
+		// removes the rate limits of windows which are over at `now` within `remaining_weight`
+		pub(crate) fn prune_rate_limits(now: T::BlockNumber, remaining_weight: Weight) -> Weight {
+			let mut consumed = T::WeightInfo::prune_rate_limits(0);
+			let per_limit = T::WeightInfo::prune_rate_limits(1).saturating_sub(consumed);
+
+			if remaining_weight.any_lt(consumed) {
+				return Weight::zero()
+			}
+
+			let window = T::OfferRateWindow::get();
+			let (mut head, tail) = <RateLimitQueueBounds<T>>::get();
+			let initial_head = head;
+
+			while head != tail && consumed.saturating_add(per_limit).all_lte(remaining_weight) {
+				match <RateLimitQueue<T>>::get(head) {
+					Some((ends_at, _)) if ends_at > now => break,
+					Some((_, who)) => {
+						<RateLimitQueue<T>>::remove(head);
+						// a newer window of the account has its own place in the queue
+						<OfferRateLimits<T>>::mutate_exists(&who, |limit| {
+							if matches!(limit, Some((start, _)) if start.saturating_add(window) <= now)
+							{
+								*limit = None;
+							}
+						});
+					},
+					None => {},
+				}
+				head = head.wrapping_add(1);
+				consumed.saturating_accrue(per_limit);
+			}
+
+			if head != initial_head {
+				<RateLimitQueueBounds<T>>::put((head, tail));
+			}
+
+			consumed
+		}
+
+		/// Number of feeless calls `who` can still make in the current era of feeless calls.
+		pub fn feeless_calls_left(who: &T::AccountId) -> u32 {
+			let used = <FeelessUsage<T>>::get(Self::feeless_era(), who);
+			T::MaxFeelessPerEra::get().saturating_sub(used)
+		}
+
+		/// Number of feeless calls all accounts together can still make in the current era of
+		/// feeless calls.
+		pub fn feeless_capacity_left() -> u32 {
+			let (era, calls) = <FeelessCalls<T>>::get();
+
+			if era == Self::feeless_era() {
+				T::FeelessEraCapacity::get().saturating_sub(calls)
+			} else {
+				T::FeelessEraCapacity::get()
+			}
+		}
+
+		// counts a feeless call of `who` against its quota and the capacity of the era, which
+		// `on_initialize` already moved to the current one
+		pub(crate) fn note_feeless_call(who: &T::AccountId) {
+			<FeelessUsage<T>>::mutate(Self::feeless_era(), who, |used| {
+				*used = used.saturating_add(1)
+			});
+			<FeelessCalls<T>>::mutate(|(_, calls)| *calls = calls.saturating_add(1));
+		}
+
+		fn feeless_era() -> T::BlockNumber {
+			<frame_system::Pallet<T>>::block_number()
+				.checked_div(&T::FeelessEra::get())
+				.unwrap_or_default()
+		}
+
+		// marks a session which came due as expired if it's still pending and queues it for
+		// removal, cancelled sessions are already gone
+		fn expire_session((offerer, offeree, session_id): SessionKey<T::AccountId>) {
+			let Some(mut session) = <ChatSessions<T>>::get((&offerer, &offeree, session_id)) else {
+				return
+			};
+
+			if session.state == SessionState::Pending {
+				session.state = SessionState::Expired;
+				<ChatSessions<T>>::insert((&offerer, &offeree, session_id), session);
+				<PendingOffersTo<T>>::remove(&offeree, (&offerer, session_id));
+
+				Self::deposit_event(Event::OfferExpired {
+					offered_by: offerer.clone(),
+					offered_to: offeree.clone(),
+					session_id,
+				});
+			}
+
+			<PruneQueueBounds<T>>::mutate(|(_, tail)| {
+				<PruneQueue<T>>::insert(*tail, (offerer, offeree, session_id));
+				*tail = tail.wrapping_add(1);
+			});
+		}
+
+		// moves a pending session into the state the offeree answered it with
+		fn settle_pending_session(
+			offerer: &T::AccountId,
+			offeree: &T::AccountId,
+			session_id: SessionId,
+			state: SessionState,
+		) -> DispatchResult {
+			<ChatSessions<T>>::try_mutate(
+				(offerer, offeree, session_id),
+				|session| match session {
+					Some(session) if session.state == SessionState::Pending => {
+						session.state = state;
+						Ok(())
+					},
+					_ => Err(Error::<T>::NoPendingOffer),
+				},
+			)?;
+			<PendingOffersTo<T>>::remove(offeree, (offerer, session_id));
+			Ok(())
+		}
+
+		// removes a pending session, its expiry entry is skipped once it comes due
+		fn take_pending_session(
+			offerer: &T::AccountId,
+			offeree: &T::AccountId,
+			session_id: SessionId,
+		) -> Result<ChatSession<T>, DispatchError> {
+			let session =
+				<ChatSessions<T>>::try_mutate_exists((offerer, offeree, session_id), |session| {
+					match session.take() {
+						Some(pending) if pending.state == SessionState::Pending => Ok(pending),
+						_ => Err(Error::<T>::NoPendingOffer),
+					}
+				})?;
+			<PendingOffersTo<T>>::remove(offeree, (offerer, session_id));
+			Ok(session)
+		}
+
+		// releases the registration of `who`, keeping both nickname maps consistent
+		pub(crate) fn do_unregister(
+			who: &T::AccountId,
+		) -> Result<ItemByAccountId<T>, DispatchError> {
+			let item =
+				<ItemByAccountIdStore<T>>::try_get(who).map_err(|_| Error::<T>::NotRegistered)?;
+
+			<ItemByAccountIdStore<T>>::remove(who);
+			<ItemByNicknameStore<T>>::remove(&item.nickname);
+			<PendingNicknameTransfers<T>>::remove(who);
+			<AddressHistory<T>>::remove(who);
+			Self::remove_prekeys(who);
+			Self::remove_devices(who);
+			T::Currency::unreserve(who, item.deposit);
+
+			Ok(item)
+		}
+
+		/// Returns the payload a messaging key has to sign to be bound to `who`, which is the
+		/// encoded account id, genesis hash and key binding nonce.
+		pub fn key_binding_payload(who: &T::AccountId) -> Vec<u8> {
+			let genesis_hash = <frame_system::Pallet<T>>::block_hash(T::BlockNumber::zero());
+			(who, genesis_hash, <KeyBindingNonces<T>>::get(who)).encode()
+		}
+
+		// checks that `address` signed the key binding payload of `who`
+		fn bind_key(
+			who: &T::AccountId,
+			address: &[u8; 32],
+			key_proof: &[u8; 64],
+		) -> DispatchResult {
+			let valid = sp_io::crypto::ed25519_verify(
+				&ed25519::Signature::from_raw(*key_proof),
+				&Self::key_binding_payload(who),
+				&ed25519::Public::from_raw(*address),
+			);
+			ensure!(valid, Error::<T>::InvalidKeyProof);
+
+			<KeyBindingNonces<T>>::mutate(who, |nonce| *nonce = nonce.wrapping_add(1));
+			Ok(())
+		}
+
+		/// Returns the owner of `nickname`, normalizing it first.
+		pub fn resolve_nickname(nickname: &[u8]) -> Option<T::AccountId> {
+			let nickname = Self::normalize_nickname(nickname).ok()?;
+			<ItemByNicknameStore<T>>::get(nickname)
+		}
+
+		/// Returns the registration of `who`.
+		pub fn reverse_lookup(who: &T::AccountId) -> Option<ItemByAccountId<T>> {
+			<ItemByAccountIdStore<T>>::try_get(who).ok()
+		}
+
+		/// Returns page `page` of the contacts stored by `who`, `CONTACTS_PAGE_SIZE` per page.
+		pub fn contacts_of(
+			who: &T::AccountId,
+			page: u32,
+		) -> Vec<(EncodedContactAddr<T>, ContactByAccountId<T>)> {
+			<ContactByAccountIdStore<T>>::iter_prefix(who)
+				.skip(page.saturating_mul(CONTACTS_PAGE_SIZE) as usize)
+				.take(CONTACTS_PAGE_SIZE as usize)
+				.collect()
+		}
+
+		/// Returns the offers made to `who` which are still pending, along with the offerer and
+		/// the session id.
+		pub fn pending_offers(
+			who: &T::AccountId,
+		) -> Vec<(T::AccountId, SessionId, ChatSession<T>)> {
+			<PendingOffersTo<T>>::iter_key_prefix(who)
+				.filter_map(|(from, session_id)| {
+					let session = <ChatSessions<T>>::get((&from, who, session_id))?;
+					Some((from, session_id, session))
+				})
+				.collect()
+		}
+
+		/// Deposit required for storing an item of `len` bytes.
+		pub fn deposit_for(len: usize) -> BalanceOf<T> {
+			let len = BalanceOf::<T>::from(len as u32);
+			T::DepositPerItem::get().saturating_add(T::DepositPerByte::get().saturating_mul(len))
+		}
+
+		fn prekeys_deposit(bundle: &PrekeyBundle<T>) -> BalanceOf<T> {
+			Self::deposit_for(
+				bundle
+					.signed_prekey
+					.encoded_size()
+					.saturating_add(bundle.one_time_prekeys.encoded_size()),
+			)
+		}
+
+		fn remove_prekeys(who: &T::AccountId) {
+			if let Some(bundle) = <PrekeyBundles<T>>::take(who) {
+				T::Currency::unreserve(who, bundle.deposit);
+			}
+		}
+
+		fn remove_devices(who: &T::AccountId) {
+			for device in <Devices<T>>::take(who) {
+				T::Currency::unreserve(who, device.deposit);
+			}
+		}
+
+		// drops the pending invite of `account` from `room`
+		fn remove_invite(room: &mut Room<T>, account: &T::AccountId) -> DispatchResult {
+			let invite = room
+				.invited
+				.iter()
+				.position(|invited| invited == account)
+				.ok_or(Error::<T>::NotInvited)?;
+			room.invited.remove(invite);
+			Ok(())
+		}
+
+		// reserves the deposit of a new room member
+		fn new_room_member(
+			who: &T::AccountId,
+			role: RoomRole,
+		) -> Result<RoomMember<T>, DispatchError> {
+			let deposit = Self::deposit_for(who.encoded_size());
+			T::Currency::reserve(who, deposit)?;
+			Ok(RoomMember { account: who.clone(), role, deposit })
+		}
+
+		// removes `who` from a room and closes the room once its last member is gone, returns
+		// whether the room was closed
+		fn remove_room_member(
+			room_id: RoomId,
+			mut room: Room<T>,
+			who: &T::AccountId,
+		) -> Result<bool, DispatchError> {
+			let index = room
+				.members
+				.iter()
+				.position(|member| &member.account == who)
+				.ok_or(Error::<T>::NotRoomMember)?;
+			let member = room.members.remove(index);
+
+			let closed = room.members.is_empty();
+			if closed {
+				<Rooms<T>>::remove(room_id);
+				T::Currency::unreserve(&room.creator, room.deposit);
+			} else {
+				ensure!(room.has_admin(), Error::<T>::LastAdmin);
+				<Rooms<T>>::insert(room_id, room);
+			}
+
+			T::Currency::unreserve(who, member.deposit);
+			Ok(closed)
+		}
+
+		// starts a new key epoch after the members of a room changed, or drops its keys once the
+		// room was closed
+		fn membership_changed(room_id: RoomId, closed: bool) {
+			if let Some(room_key) = <RoomKeys<T>>::take(room_id) {
+				T::Currency::unreserve(&room_key.posted_by, room_key.deposit);
+			}
+
+			if closed {
+				<RoomKeyEpochs<T>>::remove(room_id);
+				Self::deposit_event(Event::RoomClosed { room_id });
+			} else {
+				let epoch = <RoomKeyEpochs<T>>::mutate(room_id, |epoch| {
+					*epoch = epoch.wrapping_add(1);
+					*epoch
+				});
+				Self::deposit_event(Event::RoomKeyRotated { room_id, epoch });
+			}
+		}
+
+		fn do_upsert_contact(
+			who: &T::AccountId,
+			contact_name: ContactEnvelope<T>,
+			contact_addr: EncodedContactAddr<T>,
+		) -> DispatchResult {
+			ensure!(contact_name.is_supported(), Error::<T>::UnsupportedEnvelope);
+
+			// an existing contact keeps its linked account and metadata
+			let mut contact = match <ContactByAccountIdStore<T>>::try_get(who, &contact_addr) {
+				Ok(contact) => contact,
+				Err(_) => {
+					<ContactCount<T>>::try_mutate(who, |count| -> DispatchResult {
+						ensure!(*count < T::MaxContacts::get(), Error::<T>::TooManyContacts);
+						*count += 1;
+						Ok(())
+					})?;
+					ContactByAccountId::default()
+				},
+			};
+			contact.name = contact_name;
+			let deposit = Self::contact_deposit(&contact_addr, &contact);
+			Self::adjust_deposit(who, contact.deposit, deposit)?;
+
+			contact.deposit = deposit;
+			<ContactByAccountIdStore<T>>::insert(who, contact_addr, contact);
+			Ok(())
+		}
+
+		fn do_remove_contact(who: &T::AccountId, contact_addr: &EncodedContactAddr<T>) {
+			if let Ok(contact) = <ContactByAccountIdStore<T>>::try_get(who, contact_addr) {
+				<ContactByAccountIdStore<T>>::remove(who, contact_addr);
+				Self::release_contact(who, contact);
+			}
+		}
+
+		// drops the index entry and the count of a removed contact and releases its deposit
+		fn release_contact(who: &T::AccountId, contact: ContactByAccountId<T>) {
+			if let Some(account) = contact.account {
+				<ContactIndex<T>>::remove(who, account);
+			}
+			<ContactCount<T>>::mutate_exists(who, |count| {
+				*count = count.map(|count| count.saturating_sub(1)).filter(|count| *count > 0);
+			});
+			T::Currency::unreserve(who, contact.deposit);
+		}
+
+		// deposit for a contact, including the linked account kept in `ContactIndex`
+		fn contact_deposit(
+			addr: &EncodedContactAddr<T>,
+			contact: &ContactByAccountId<T>,
+		) -> BalanceOf<T> {
+			// the fixed size envelope headers are covered by the per item deposit
+			let name_len = contact.name.ciphertext.len();
+			let note_len = contact.note.as_ref().map_or(0, |note| note.ciphertext.len());
+			let account_len = contact.account.as_ref().map_or(0, |account| account.encoded_size());
+			let groups_len = contact.groups.len().saturating_mul(sp_std::mem::size_of::<GroupId>());
+			Self::deposit_for(
+				name_len
+					.saturating_add(addr.len())
+					.saturating_add(account_len)
+					.saturating_add(groups_len)
+					.saturating_add(note_len),
+			)
+		}
+
+		fn contact_groups_deposit(groups: &ContactGroups<T>) -> BalanceOf<T> {
+			if groups.groups.is_empty() {
+				Zero::zero()
+			} else {
+				Self::deposit_for(groups.groups.encoded_size())
+			}
+		}
+
+		// reserves the deposit for changed contact groups and stores them
+		fn update_contact_groups(
+			who: &T::AccountId,
+			mut groups: ContactGroups<T>,
+		) -> DispatchResult {
+			let deposit = Self::contact_groups_deposit(&groups);
+			Self::adjust_deposit(who, groups.deposit, deposit)?;
+			groups.deposit = deposit;
+			<ContactGroupsOf<T>>::insert(who, groups);
+			Ok(())
+		}
+
+		// reserves or unreserves the difference between the old and the new deposit
+		fn adjust_deposit(
+			who: &T::AccountId,
+			old: BalanceOf<T>,
+			new: BalanceOf<T>,
+		) -> DispatchResult {
+			if new > old {
+				T::Currency::reserve(who, new.saturating_sub(old))?;
+			} else if old > new {
+				T::Currency::unreserve(who, old.saturating_sub(new));
+			}
 			Ok(())
 		}
 	}
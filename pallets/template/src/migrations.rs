@@ -0,0 +1,880 @@
+//! Storage migrations for the template pallet.
+
+use super::*;
+use frame_support::{
+	pallet_prelude::*,
+	sp_runtime::traits::{Saturating, Zero},
+	sp_std::{
+		collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+		vec::Vec,
+	},
+	traits::{OnRuntimeUpgrade, ReservableCurrency},
+};
+
+// Strips the zero padding clients used to fill the old fixed-size arrays with.
+fn trim_padding(bytes: &[u8]) -> &[u8] {
+	let len = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+	&bytes[..len]
+}
+
+pub mod v1 {
+	use super::*;
+
+	pub(crate) mod v0 {
+		use super::*;
+
+		#[derive(Encode, Decode)]
+		pub struct ContactByAccountId {
+			pub name: [u8; 1000],
+		}
+
+		#[derive(Encode, Decode)]
+		pub struct ItemByAccountId {
+			pub address: [u8; 32],
+			pub nickname: [u8; 21],
+		}
+
+		#[frame_support::storage_alias]
+		pub type ContactByAccountIdStore<T: Config> = StorageDoubleMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			<T as frame_system::Config>::AccountId,
+			Blake2_128Concat,
+			[u8; 1000],
+			ContactByAccountId,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type ItemByNicknameStore<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			[u8; 21],
+			<T as frame_system::Config>::AccountId,
+		>;
+
+		#[frame_support::storage_alias]
+		pub type ItemByAccountIdStore<T: Config> = StorageMap<
+			Pallet<T>,
+			Blake2_128Concat,
+			<T as frame_system::Config>::AccountId,
+			ItemByAccountId,
+		>;
+	}
+
+	#[derive(Encode, Decode)]
+	pub struct ContactByAccountId<T: Config> {
+		pub name: EncodedContactName<T>,
+	}
+
+	#[derive(Encode, Decode)]
+	pub struct ItemByAccountId<T: Config> {
+		pub address: [u8; 32],
+		pub nickname: Nickname<T>,
+	}
+
+	#[frame_support::storage_alias]
+	pub type ContactByAccountIdStore<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Blake2_128Concat,
+		EncodedContactAddr<T>,
+		ContactByAccountId<T>,
+	>;
+
+	#[frame_support::storage_alias]
+	pub type ItemByAccountIdStore<T: Config> = StorageMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		ItemByAccountId<T>,
+	>;
+
+	/// Moves contacts and nicknames from zero padded fixed-size arrays to bounded vectors.
+	///
+	/// Entries which don't fit into the configured bounds are dropped.
+	pub struct MigrateToV1<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV1<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 0 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut reads = 1_u64;
+			let mut writes = 1_u64;
+
+			// keys change their encoding, so old entries have to be drained before re-inserting
+			let contacts: Vec<_> = v0::ContactByAccountIdStore::<T>::drain().collect();
+			for (owner, addr, contact) in contacts {
+				reads += 1;
+				writes += 1;
+
+				let (Ok(addr), Ok(name)) = (
+					EncodedContactAddr::<T>::try_from(trim_padding(&addr).to_vec()),
+					EncodedContactName::<T>::try_from(trim_padding(&contact.name).to_vec()),
+				) else {
+					continue
+				};
+
+				ContactByAccountIdStore::<T>::insert(owner, addr, ContactByAccountId::<T> { name });
+				writes += 1;
+			}
+
+			let nicknames: Vec<_> = v0::ItemByNicknameStore::<T>::drain().collect();
+			for (nickname, owner) in nicknames {
+				reads += 1;
+				writes += 1;
+
+				if let Ok(nickname) = Nickname::<T>::try_from(trim_padding(&nickname).to_vec()) {
+					ItemByNicknameStore::<T>::insert(nickname, owner);
+					writes += 1;
+				}
+			}
+
+			ItemByAccountIdStore::<T>::translate::<v0::ItemByAccountId, _>(|_, old| {
+				reads += 1;
+				writes += 1;
+
+				Nickname::<T>::try_from(trim_padding(&old.nickname).to_vec())
+					.ok()
+					.map(|nickname| ItemByAccountId::<T> { address: old.address, nickname })
+			});
+
+			StorageVersion::new(1).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let contacts = v0::ContactByAccountIdStore::<T>::iter_keys().count() as u32;
+			let registrations = v0::ItemByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok((contacts, registrations).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let (contacts, registrations): (u32, u32) =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 1, "storage version not updated");
+			ensure!(
+				ContactByAccountIdStore::<T>::iter_keys().count() as u32 <= contacts,
+				"contacts were not migrated"
+			);
+			ensure!(
+				ItemByAccountIdStore::<T>::iter_keys().count() as u32 <= registrations,
+				"registrations were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v2 {
+	use super::*;
+
+	#[derive(Encode, Decode)]
+	pub struct ContactByAccountId<T: Config> {
+		pub name: EncodedContactName<T>,
+		pub deposit: BalanceOf<T>,
+	}
+
+	#[frame_support::storage_alias]
+	pub type ContactByAccountIdStore<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Blake2_128Concat,
+		EncodedContactAddr<T>,
+		ContactByAccountId<T>,
+	>;
+
+	/// Adds a zero storage deposit to contacts and nickname registrations made before deposits
+	/// were introduced.
+	pub struct MigrateToV2<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV2<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 1 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0_u64;
+
+			ContactByAccountIdStore::<T>::translate_values::<v1::ContactByAccountId<T>, _>(|old| {
+				translated += 1;
+				Some(ContactByAccountId { name: old.name, deposit: Zero::zero() })
+			});
+
+			crate::ItemByAccountIdStore::<T>::translate_values::<v1::ItemByAccountId<T>, _>(
+				|old| {
+					translated += 1;
+					Some(ItemByAccountId {
+						address: old.address,
+						nickname: old.nickname,
+						deposit: Zero::zero(),
+					})
+				},
+			);
+
+			StorageVersion::new(2).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let contacts = v1::ContactByAccountIdStore::<T>::iter_keys().count() as u32;
+			let registrations = v1::ItemByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok((contacts, registrations).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let (contacts, registrations): (u32, u32) =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 2, "storage version not updated");
+			ensure!(
+				ContactByAccountIdStore::<T>::iter_values().count() as u32 == contacts,
+				"contacts were not migrated"
+			);
+			ensure!(
+				crate::ItemByAccountIdStore::<T>::iter_values().count() as u32 == registrations,
+				"registrations were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v3 {
+	use super::*;
+
+	#[derive(Encode, Decode)]
+	pub struct ChatSession<T: Config> {
+		pub offer: OfferPayload<T>,
+		pub welcome_msg: WelcomeMsg<T>,
+		pub state: SessionState,
+		pub created_at: T::BlockNumber,
+	}
+
+	#[frame_support::storage_alias]
+	pub type ChatSessions<T: Config> = StorageNMap<
+		Pallet<T>,
+		(
+			NMapKey<Blake2_128Concat, <T as frame_system::Config>::AccountId>,
+			NMapKey<Blake2_128Concat, <T as frame_system::Config>::AccountId>,
+			NMapKey<Twox64Concat, SessionId>,
+		),
+		ChatSession<T>,
+	>;
+
+	/// Adds an empty linked account to contacts.
+	pub struct MigrateToV3<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV3<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 2 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0_u64;
+
+			v4::ContactByAccountIdStore::<T>::translate_values::<v2::ContactByAccountId<T>, _>(
+				|old| {
+					translated += 1;
+					Some(v4::ContactByAccountId {
+						name: old.name,
+						account: None,
+						deposit: old.deposit,
+					})
+				},
+			);
+
+			StorageVersion::new(3).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let contacts = v2::ContactByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok(contacts.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let contacts: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 3, "storage version not updated");
+			ensure!(
+				v4::ContactByAccountIdStore::<T>::iter_values().count() as u32 == contacts,
+				"contacts were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v4 {
+	use super::*;
+
+	#[derive(Encode, Decode)]
+	pub struct ContactByAccountId<T: Config> {
+		pub name: EncodedContactName<T>,
+		pub account: Option<T::AccountId>,
+		pub deposit: BalanceOf<T>,
+	}
+
+	#[frame_support::storage_alias]
+	pub type ContactByAccountIdStore<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Blake2_128Concat,
+		EncodedContactAddr<T>,
+		ContactByAccountId<T>,
+	>;
+
+	/// Marks chat sessions opened before rooms were introduced as direct ones.
+	pub struct MigrateToV4<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV4<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 3 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0_u64;
+
+			v7::ChatSessions::<T>::translate_values::<v3::ChatSession<T>, _>(|old| {
+				translated += 1;
+				Some(v7::ChatSession {
+					offer: old.offer,
+					welcome_msg: old.welcome_msg,
+					state: old.state,
+					created_at: old.created_at,
+					room: None,
+				})
+			});
+
+			StorageVersion::new(4).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let sessions = v3::ChatSessions::<T>::iter_keys().count() as u32;
+
+			Ok(sessions.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let sessions: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 4, "storage version not updated");
+			ensure!(
+				v7::ChatSessions::<T>::iter_values().count() as u32 == sessions,
+				"chat sessions were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v5 {
+	use super::*;
+
+	#[derive(Encode, Decode)]
+	pub struct ContactByAccountId<T: Config> {
+		pub name: ContactEnvelope<T>,
+		pub account: Option<T::AccountId>,
+		pub deposit: BalanceOf<T>,
+	}
+
+	#[frame_support::storage_alias]
+	pub type ContactByAccountIdStore<T: Config> = StorageDoubleMap<
+		Pallet<T>,
+		Blake2_128Concat,
+		<T as frame_system::Config>::AccountId,
+		Blake2_128Concat,
+		EncodedContactAddr<T>,
+		ContactByAccountId<T>,
+	>;
+
+	/// Wraps contact names stored before envelopes were introduced into plain envelopes.
+	pub struct MigrateToV5<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV5<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 4 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0_u64;
+
+			v5::ContactByAccountIdStore::<T>::translate_values::<v4::ContactByAccountId<T>, _>(
+				|old| {
+					translated += 1;
+					Some(v5::ContactByAccountId {
+						name: EncryptedEnvelope::plain(old.name),
+						account: old.account,
+						deposit: old.deposit,
+					})
+				},
+			);
+
+			StorageVersion::new(5).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let contacts = v4::ContactByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok(contacts.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let contacts: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 5, "storage version not updated");
+			ensure!(
+				v5::ContactByAccountIdStore::<T>::iter_values().count() as u32 == contacts,
+				"contacts were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v6 {
+	use super::*;
+
+	/// Adds empty groups, flags and note to contacts.
+	pub struct MigrateToV6<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV6<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 5 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0_u64;
+
+			crate::ContactByAccountIdStore::<T>::translate_values::<v5::ContactByAccountId<T>, _>(
+				|old| {
+					translated += 1;
+					Some(ContactByAccountId {
+						name: old.name,
+						account: old.account,
+						groups: Default::default(),
+						flags: 0,
+						note: None,
+						deposit: old.deposit,
+					})
+				},
+			);
+
+			StorageVersion::new(6).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let contacts = v5::ContactByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok(contacts.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let contacts: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 6, "storage version not updated");
+			ensure!(
+				crate::ContactByAccountIdStore::<T>::iter_values().count() as u32 == contacts,
+				"contacts were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v7 {
+	use super::*;
+
+	#[derive(Encode, Decode)]
+	pub struct ChatSession<T: Config> {
+		pub offer: OfferPayload<T>,
+		pub welcome_msg: WelcomeMsg<T>,
+		pub state: SessionState,
+		pub created_at: T::BlockNumber,
+		pub room: Option<RoomId>,
+	}
+
+	#[frame_support::storage_alias]
+	pub type ChatSessions<T: Config> = StorageNMap<
+		Pallet<T>,
+		(
+			NMapKey<Blake2_128Concat, <T as frame_system::Config>::AccountId>,
+			NMapKey<Blake2_128Concat, <T as frame_system::Config>::AccountId>,
+			NMapKey<Twox64Concat, SessionId>,
+		),
+		ChatSession<T>,
+	>;
+
+	/// Counts the contacts every account stored before the count was tracked.
+	pub struct MigrateToV7<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV7<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 6 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut reads = 1_u64;
+			let mut writes = 1_u64;
+
+			let mut counts = BTreeMap::<T::AccountId, u32>::new();
+			for (owner, _) in crate::ContactByAccountIdStore::<T>::iter_keys() {
+				reads += 1;
+				*counts.entry(owner).or_default() += 1;
+			}
+			for (owner, count) in counts {
+				writes += 1;
+				crate::ContactCount::<T>::insert(owner, count);
+			}
+
+			StorageVersion::new(7).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let contacts = crate::ContactByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok(contacts.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let contacts: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 7, "storage version not updated");
+			ensure!(
+				crate::ContactCount::<T>::iter_values().sum::<u32>() == contacts,
+				"contact counts don't match the contacts"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v8 {
+	use super::*;
+
+	/// Marks chat sessions opened before devices were introduced as offered to all devices.
+	pub struct MigrateToV8<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV8<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 7 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut translated = 0_u64;
+
+			crate::ChatSessions::<T>::translate_values::<v7::ChatSession<T>, _>(|old| {
+				translated += 1;
+				Some(ChatSession {
+					offer: old.offer,
+					welcome_msg: old.welcome_msg,
+					state: old.state,
+					created_at: old.created_at,
+					room: old.room,
+					device: None,
+				})
+			});
+
+			StorageVersion::new(8).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(translated + 1, translated + 1)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let sessions = v7::ChatSessions::<T>::iter_keys().count() as u32;
+
+			Ok(sessions.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let sessions: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 8, "storage version not updated");
+			ensure!(
+				crate::ChatSessions::<T>::iter_values().count() as u32 == sessions,
+				"chat sessions were not migrated"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v9 {
+	use super::*;
+
+	/// Indexes the pending offers of every offeree in `PendingOffersTo`.
+	pub struct MigrateToV9<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV9<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 8 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut reads = 1_u64;
+			let mut writes = 1_u64;
+
+			for ((offerer, offeree, session_id), session) in crate::ChatSessions::<T>::iter() {
+				reads += 1;
+				if session.state == SessionState::Pending {
+					writes += 1;
+					crate::PendingOffersTo::<T>::insert(offeree, (offerer, session_id), ());
+				}
+			}
+
+			StorageVersion::new(9).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let pending = crate::ChatSessions::<T>::iter_values()
+				.filter(|session| session.state == SessionState::Pending)
+				.count() as u32;
+
+			Ok(pending.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let pending: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 9, "storage version not updated");
+			ensure!(
+				crate::PendingOffersTo::<T>::iter_keys().count() as u32 == pending,
+				"pending offers were not indexed"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v10 {
+	use super::*;
+
+	#[frame_support::storage_alias]
+	pub type SessionExpiries<T: Config> = StorageMap<
+		Pallet<T>,
+		Twox64Concat,
+		<T as frame_system::Config>::BlockNumber,
+		BoundedVec<
+			SessionKey<<T as frame_system::Config>::AccountId>,
+			<T as Config>::MaxExpiriesPerBlock,
+		>,
+		ValueQuery,
+	>;
+
+	/// Moves the sessions of the per block expiry buckets into the `ExpiryQueue`, ordered by the
+	/// block they expire at.
+	pub struct MigrateToV10<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV10<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 9 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut buckets = SessionExpiries::<T>::drain().collect::<Vec<_>>();
+			buckets.sort_by_key(|(expires_at, _)| *expires_at);
+
+			let reads = buckets.len() as u64 + 2;
+			let mut writes = buckets.len() as u64 + 2;
+
+			let (head, mut tail) = crate::ExpiryQueueBounds::<T>::get();
+			for (expires_at, keys) in buckets {
+				for key in keys {
+					writes += 1;
+					crate::ExpiryQueue::<T>::insert(tail, (expires_at, key));
+					tail = tail.wrapping_add(1);
+				}
+			}
+			crate::ExpiryQueueBounds::<T>::put((head, tail));
+
+			StorageVersion::new(10).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let expiring =
+				SessionExpiries::<T>::iter_values().map(|keys| keys.len()).sum::<usize>();
+
+			Ok((expiring as u32).encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let expiring: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 10, "storage version not updated");
+			ensure!(SessionExpiries::<T>::iter_keys().next().is_none(), "expiries were not moved");
+			ensure!(
+				crate::ExpiryQueue::<T>::iter_keys().count() as u32 == expiring,
+				"expiries were not queued"
+			);
+
+			Ok(())
+		}
+	}
+}
+
+pub mod v11 {
+	use super::*;
+
+	/// Normalizes the nicknames registered before they were validated.
+	///
+	/// No registration time is stored, so when several nicknames normalize to the same one it
+	/// goes to the account which already registered it in normalized form, otherwise to the
+	/// lowest account id. The other accounts and those with invalid nicknames are unregistered
+	/// and get their deposits back.
+	pub struct MigrateToV11<T>(PhantomData<T>);
+
+	impl<T: Config> OnRuntimeUpgrade for MigrateToV11<T> {
+		fn on_runtime_upgrade() -> Weight {
+			if Pallet::<T>::on_chain_storage_version() != 10 {
+				return T::DbWeight::get().reads(1)
+			}
+
+			let mut registrations = crate::ItemByAccountIdStore::<T>::iter()
+				.map(|(owner, item)| {
+					let normalized = Pallet::<T>::normalize_nickname(&item.nickname).ok();
+					let changed = normalized.as_ref() != Some(&item.nickname);
+					(changed, owner, item, normalized)
+				})
+				.collect::<Vec<_>>();
+			registrations.sort_by(|a, b| (a.0, &a.1).cmp(&(b.0, &b.1)));
+
+			let mut reads = registrations.len() as u64 + 1;
+			let mut writes = 1_u64;
+
+			let mut claimed = BTreeSet::new();
+			for (changed, owner, mut item, normalized) in registrations {
+				match normalized {
+					Some(nickname) if claimed.insert(nickname.clone()) => {
+						if !changed {
+							continue
+						}
+
+						let deposit = Pallet::<T>::deposit_for(
+							nickname.len().saturating_add(item.address.len()),
+						);
+						// registrations from before deposits don't have to pay up now
+						if deposit < item.deposit {
+							T::Currency::unreserve(&owner, item.deposit.saturating_sub(deposit));
+							item.deposit = deposit;
+						}
+
+						crate::ItemByNicknameStore::<T>::remove(&item.nickname);
+						crate::ItemByNicknameStore::<T>::insert(&nickname, &owner);
+						item.nickname = nickname;
+						crate::ItemByAccountIdStore::<T>::insert(&owner, item);
+
+						reads += 1;
+						writes += 4;
+					},
+					_ => {
+						let _ = Pallet::<T>::do_unregister(&owner);
+
+						reads += 4;
+						writes += 9;
+					},
+				}
+			}
+
+			StorageVersion::new(11).put::<Pallet<T>>();
+
+			T::DbWeight::get().reads_writes(reads, writes)
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn pre_upgrade() -> Result<Vec<u8>, &'static str> {
+			let registrations = crate::ItemByAccountIdStore::<T>::iter_keys().count() as u32;
+
+			Ok(registrations.encode())
+		}
+
+		#[cfg(feature = "try-runtime")]
+		fn post_upgrade(state: Vec<u8>) -> Result<(), &'static str> {
+			let registrations: u32 =
+				Decode::decode(&mut &state[..]).map_err(|_| "invalid pre-upgrade state")?;
+
+			ensure!(Pallet::<T>::on_chain_storage_version() == 11, "storage version not updated");
+
+			let mut kept = 0_u32;
+			for (owner, item) in crate::ItemByAccountIdStore::<T>::iter() {
+				kept += 1;
+				ensure!(
+					Pallet::<T>::normalize_nickname(&item.nickname).ok() ==
+						Some(item.nickname.clone()),
+					"nickname was not normalized"
+				);
+				ensure!(
+					crate::ItemByNicknameStore::<T>::get(&item.nickname) == Some(owner),
+					"nickname maps are inconsistent"
+				);
+			}
+			ensure!(kept <= registrations, "registrations were added");
+			ensure!(
+				crate::ItemByNicknameStore::<T>::iter_keys().count() as u32 == kept,
+				"released nicknames are still registered"
+			);
+
+			Ok(())
+		}
+	}
+}
@@ -1,7 +1,8 @@
 use crate as pallet_template;
-use frame_support::traits::{ConstU16, ConstU64};
+use frame_support::traits::{ConstU16, ConstU32, ConstU64, GenesisBuild};
 use frame_system as system;
-use sp_core::H256;
+use sp_core::{ed25519, Pair, H256};
+use sp_keystore::{testing::KeyStore, KeystoreExt};
 use sp_runtime::{
 	testing::Header,
 	traits::{BlakeTwo256, IdentityLookup},
@@ -18,6 +19,7 @@ frame_support::construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic,
 	{
 		System: frame_system,
+		Balances: pallet_balances,
 		TemplateModule: pallet_template,
 	}
 );
@@ -40,7 +42,7 @@ impl system::Config for Test {
 	type BlockHashCount = ConstU64<250>;
 	type Version = ();
 	type PalletInfo = PalletInfo;
-	type AccountData = ();
+	type AccountData = pallet_balances::AccountData<u64>;
 	type OnNewAccount = ();
 	type OnKilledAccount = ();
 	type SystemWeightInfo = ();
@@ -49,11 +51,88 @@ impl system::Config for Test {
 	type MaxConsumers = frame_support::traits::ConstU32<16>;
 }
 
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type RuntimeEvent = RuntimeEvent;
+	type DustRemoval = ();
+	type ExistentialDeposit = ConstU64<1>;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
 impl pallet_template::Config for Test {
 	type RuntimeEvent = RuntimeEvent;
+	type OfferTtl = ConstU64<10>;
+	type MaxExpiriesPerBlock = ConstU32<2>;
+	type MaxOffersPerWindow = ConstU32<5>;
+	type OfferRateWindow = ConstU64<10>;
+	type FeelessEra = ConstU64<100>;
+	type MaxFeelessPerEra = ConstU32<2>;
+	type FeelessEraCapacity = ConstU32<3>;
+	type FeelessPowDifficulty = ConstU32<4>;
+	type FeelessProofLifetime = ConstU64<20>;
+	type MaxRoomMembers = ConstU32<3>;
+	type MaxRoomNameLen = ConstU32<32>;
+	type MaxRoomKeyLen = ConstU32<64>;
+	type MaxOfferLen = ConstU32<2048>;
+	type MaxWelcomeMsgLen = ConstU32<300>;
+	type MaxAnswerLen = ConstU32<2048>;
+	type MaxIceCandidatesLen = ConstU32<2048>;
+	type MaxContactNameLen = ConstU32<1000>;
+	type MaxContactAddrLen = ConstU32<1000>;
+	type MaxContacts = ConstU32<4>;
+	type MaxContactBatch = ConstU32<3>;
+	type MaxContactGroups = ConstU32<2>;
+	type MaxContactGroupNameLen = ConstU32<32>;
+	type MaxContactNoteLen = ConstU32<64>;
+	type MaxNicknameLen = ConstU32<21>;
+	type Currency = Balances;
+	type DepositPerItem = ConstU64<10>;
+	type DepositPerByte = ConstU64<1>;
+	type MaxAddressHistory = ConstU32<2>;
+	type MaxOneTimePrekeys = ConstU32<3>;
+	type MinOneTimePrekeys = ConstU32<2>;
+	type MaxDevices = ConstU32<2>;
+	type MaxDeviceLabelLen = ConstU32<16>;
+	type NicknameValidator = crate::nickname::LowercaseAscii<ConstU32<3>>;
+	type ReservedNicknameOrigin = system::EnsureRoot<u64>;
+	type WeightInfo = ();
 }
 
 // Build genesis storage according to the mock runtime.
 pub fn new_test_ext() -> sp_io::TestExternalities {
-	system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> {
+		balances: vec![(1, 100_000), (2, 100_000), (3, 100_000), (4, 100_000)],
+	}
+	.assimilate_storage(&mut t)
+	.unwrap();
+
+	let mut ext = sp_io::TestExternalities::new(t);
+	// benchmarks generate messaging keys in the keystore
+	ext.register_extension(KeystoreExt(std::sync::Arc::new(KeyStore::new())));
+	ext
+}
+
+/// Messaging key derived from `seed`.
+pub fn messaging_key(seed: u8) -> ed25519::Pair {
+	ed25519::Pair::from_seed(&[seed; 32])
+}
+
+/// Address published for the messaging key derived from `seed`.
+pub fn address(seed: u8) -> [u8; 32] {
+	messaging_key(seed).public().0
+}
+
+/// Proof binding the messaging key derived from `seed` to `who`.
+pub fn key_proof(who: u64, seed: u8) -> [u8; 64] {
+	messaging_key(seed).sign(&TemplateModule::key_binding_payload(&who)).0
+}
+
+/// Prekey `key` signed by the messaging key derived from `seed`.
+pub fn signed_prekey(seed: u8, key: [u8; 32]) -> pallet_template::SignedPrekey {
+	pallet_template::SignedPrekey { key, signature: messaging_key(seed).sign(&key).0 }
 }
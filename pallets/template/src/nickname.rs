@@ -0,0 +1,38 @@
+//! Nickname validation rules.
+
+use frame_support::{
+	sp_std::{marker::PhantomData, vec::Vec},
+	traits::Get,
+};
+
+/// Checks a nickname before it is claimed and normalizes it into the form it is stored under.
+pub trait NicknameValidator {
+	/// Returns the normalized nickname, or `None` if it is not allowed.
+	fn validate(nickname: &[u8]) -> Option<Vec<u8>>;
+}
+
+/// Accepts any nickname as is.
+impl NicknameValidator for () {
+	fn validate(nickname: &[u8]) -> Option<Vec<u8>> {
+		Some(nickname.to_vec())
+	}
+}
+
+/// Allows lowercase ASCII letters, digits and `_` in nicknames of at least `MinLen` bytes.
+///
+/// Uppercase letters are lowercased, so `Alice` and `alice` are the same nickname.
+pub struct LowercaseAscii<MinLen>(PhantomData<MinLen>);
+
+impl<MinLen: Get<u32>> NicknameValidator for LowercaseAscii<MinLen> {
+	fn validate(nickname: &[u8]) -> Option<Vec<u8>> {
+		if nickname.len() < MinLen::get() as usize {
+			return None
+		}
+
+		let normalized = nickname.to_ascii_lowercase();
+		normalized
+			.iter()
+			.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == b'_')
+			.then_some(normalized)
+	}
+}
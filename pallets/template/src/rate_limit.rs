@@ -0,0 +1,84 @@
+//! Transaction pool level check of the offer rate limit.
+
+use crate::{Call, Config, Pallet};
+use codec::{Decode, Encode};
+use frame_support::{
+	sp_runtime::{
+		traits::{DispatchInfoOf, SignedExtension},
+		transaction_validity::{
+			InvalidTransaction, TransactionValidity, TransactionValidityError, ValidTransaction,
+		},
+	},
+	sp_std::{fmt, marker::PhantomData},
+	traits::IsSubType,
+};
+use scale_info::TypeInfo;
+
+/// Custom validity error of an offer over the sender's rate limit.
+pub const RATE_LIMITED: u8 = 1;
+
+/// Rejects `offer_chat` transactions of senders over their rate limit before they get into a
+/// block.
+///
+/// `offer_chat` enforces the limit by itself, this only keeps such transactions out of the pool.
+#[derive(Encode, Decode, Clone, Eq, PartialEq, TypeInfo)]
+#[scale_info(skip_type_params(T))]
+pub struct CheckOfferRateLimit<T: Config + Send + Sync>(PhantomData<T>);
+
+impl<T: Config + Send + Sync> CheckOfferRateLimit<T> {
+	pub fn new() -> Self {
+		Self(PhantomData)
+	}
+}
+
+impl<T: Config + Send + Sync> Default for CheckOfferRateLimit<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Config + Send + Sync> fmt::Debug for CheckOfferRateLimit<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "CheckOfferRateLimit")
+	}
+}
+
+impl<T: Config + Send + Sync> SignedExtension for CheckOfferRateLimit<T>
+where
+	T::RuntimeCall: IsSubType<Call<T>>,
+{
+	const IDENTIFIER: &'static str = "CheckOfferRateLimit";
+	type AccountId = T::AccountId;
+	type Call = T::RuntimeCall;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(
+		&self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		_info: &DispatchInfoOf<Self::Call>,
+		_len: usize,
+	) -> TransactionValidity {
+		if let Some(Call::offer_chat { .. }) = call.is_sub_type() {
+			if Pallet::<T>::is_offer_rate_limited(who) {
+				return Err(InvalidTransaction::Custom(RATE_LIMITED).into())
+			}
+		}
+		Ok(ValidTransaction::default())
+	}
+
+	fn pre_dispatch(
+		self,
+		who: &Self::AccountId,
+		call: &Self::Call,
+		info: &DispatchInfoOf<Self::Call>,
+		len: usize,
+	) -> Result<(), TransactionValidityError> {
+		self.validate(who, call, info, len).map(|_| ())
+	}
+}
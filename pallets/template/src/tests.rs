@@ -1,6 +1,49 @@
-use crate::{mock::*, ContactByAccountId, Error, Event, ItemByAccountId};
-use frame_support::{assert_noop, assert_ok};
+use crate::{
+	feeless::{
+		FeelessProof, FEELESS_CAPACITY_EXHAUSTED, FEELESS_QUOTA_EXHAUSTED, INSUFFICIENT_POW,
+		UNKNOWN_POW_BLOCK,
+	},
+	migrations::{v1, v10, v11, v2, v3, v4, v5, v6, v7, v8, v9},
+	mock::*,
+	rate_limit::RATE_LIMITED,
+	ChargeOrFeeless, ChatSession, CheckOfferRateLimit, ContactByAccountId, ContactByAccountIdStore,
+	ContactCount, ContactEnvelope, Device, EncryptedEnvelope, Error, Event, InboundPolicy,
+	ItemByAccountId, RoomKeyCopies, RoomKeyEpochs, RoomRole, SessionState, SignedPrekey,
+	CONTACT_FAVOURITE,
+};
+use codec::Encode;
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::GetDispatchInfo,
+	sp_io::hashing::blake2_256,
+	sp_std::collections::btree_map::BTreeMap,
+	traits::{Get, GetStorageVersion, Hooks, OnRuntimeUpgrade, ReservableCurrency, StorageVersion},
+	weights::Weight,
+	BoundedVec,
+};
 use frame_system::ensure_signed;
+use sp_core::H256;
+use sp_runtime::{
+	traits::SignedExtension, transaction_validity::InvalidTransaction, DispatchError,
+};
+
+fn bounded<S: Get<u32>>(bytes: &[u8]) -> BoundedVec<u8, S> {
+	bytes.to_vec().try_into().expect("test payload fits into the bound")
+}
+
+fn batch<I: Clone, S: Get<u32>>(items: &[I]) -> BoundedVec<I, S> {
+	items.to_vec().try_into().expect("test batch fits into the bound")
+}
+
+// envelope with made up nonce and MAC, the pallet doesn't open them
+fn envelope<S: Get<u32>>(content: &[u8]) -> EncryptedEnvelope<BoundedVec<u8, S>> {
+	EncryptedEnvelope {
+		algorithm: pallet_template_envelope::ALGORITHM_XCHACHA20_POLY1305,
+		nonce: [1; pallet_template_envelope::NONCE_LEN],
+		ciphertext: bounded(content),
+		mac: [1; pallet_template_envelope::MAC_LEN],
+	}
+}
 
 #[test]
 fn test_upsert_contact() {
@@ -10,8 +53,8 @@ fn test_upsert_contact() {
 		let sender = RuntimeOrigin::signed(1);
 
 		let sender_addr = ensure_signed(sender.clone()).unwrap();
-		let nickname = [4_u8; 1000];
-		let address = [1_u8; 1000];
+		let nickname = envelope(&[4_u8; 1000]);
+		let address = bounded(&[1_u8; 1000]);
 
 		assert_ok!(TemplateModule::upsert_contact(
 			sender.clone(),
@@ -21,9 +64,13 @@ fn test_upsert_contact() {
 
 		let addr_resp = TemplateModule::get_contact_by_account_id(sender_addr, address.clone());
 
-		assert_eq!(ContactByAccountId { name: nickname }, addr_resp);
+		assert_eq!(
+			ContactByAccountId { name: nickname, deposit: 2010, ..Default::default() },
+			addr_resp
+		);
+		assert_eq!(Balances::reserved_balance(sender_addr), 2010);
 
-		let nickname2 = [2_u8; 1000];
+		let nickname2 = envelope(&[2_u8; 1000]);
 
 		assert_ok!(TemplateModule::upsert_contact(
 			sender.clone(),
@@ -33,7 +80,11 @@ fn test_upsert_contact() {
 
 		let addr_resp = TemplateModule::get_contact_by_account_id(sender_addr, address.clone());
 
-		assert_eq!(ContactByAccountId { name: nickname2 }, addr_resp);
+		assert_eq!(
+			ContactByAccountId { name: nickname2, deposit: 2010, ..Default::default() },
+			addr_resp
+		);
+		assert_eq!(Balances::reserved_balance(sender_addr), 2010);
 
 		assert_ok!(TemplateModule::remove_contact(sender, address.clone()));
 
@@ -41,6 +92,7 @@ fn test_upsert_contact() {
 			ContactByAccountId::default(),
 			TemplateModule::get_contact_by_account_id(sender_addr, address.clone())
 		);
+		assert_eq!(Balances::reserved_balance(sender_addr), 0);
 	})
 }
 
@@ -52,10 +104,15 @@ fn test_register() {
 		let sender = RuntimeOrigin::signed(1);
 
 		let sender_addr = ensure_signed(sender.clone()).unwrap();
-		let nickname = [123_u8; 21];
-		let address = [1_u8; 32];
+		let nickname = bounded(&[b'a'; 21]);
+		let address = address(1);
 
-		assert_ok!(TemplateModule::register(sender, nickname.clone(), address.clone()));
+		assert_ok!(TemplateModule::register(
+			sender,
+			nickname.clone(),
+			address.clone(),
+			key_proof(1, 1)
+		));
 
 		let addr_resp = TemplateModule::get_address_by_nickname(nickname.clone());
 
@@ -63,7 +120,406 @@ fn test_register() {
 
 		let item_by_account_id = TemplateModule::get_address_by_account_id(sender_addr);
 
-		assert_eq!(item_by_account_id, ItemByAccountId { address, nickname })
+		assert_eq!(item_by_account_id, ItemByAccountId { address, nickname, deposit: 63 });
+		assert_eq!(Balances::reserved_balance(sender_addr), 63);
+	})
+}
+
+#[test]
+fn upsert_contact_adjusts_deposit() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let address = bounded(&[1_u8; 100]);
+
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 100]),
+			address.clone()
+		));
+		assert_eq!(Balances::reserved_balance(1), 210);
+
+		// a shorter name releases part of the deposit
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 50]),
+			address.clone()
+		));
+		assert_eq!(Balances::reserved_balance(1), 160);
+
+		// a longer one reserves more
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 200]),
+			address
+		));
+		assert_eq!(Balances::reserved_balance(1), 310);
+	})
+}
+
+#[test]
+fn contact_names_are_sealed_by_clients() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let key = [9_u8; 32];
+		let address = bounded(&[1_u8; 100]);
+		let sealed = pallet_template_envelope::seal(&key, [3; 24], b"Bob").map(|c| bounded(&c));
+
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			sealed.clone(),
+			address.clone()
+		));
+		// the envelope header is covered by the per item deposit
+		assert_eq!(Balances::reserved_balance(1), 113);
+
+		let stored = TemplateModule::get_contact_by_account_id(1, address).name;
+		assert_eq!(stored, sealed);
+		assert_eq!(pallet_template_envelope::open(&key, &stored), Ok(b"Bob".to_vec()));
+		assert_eq!(
+			pallet_template_envelope::open(&[8; 32], &stored),
+			Err(pallet_template_envelope::Error::InvalidMac)
+		);
+
+		let unknown = EncryptedEnvelope { algorithm: 7, ..envelope(&[4_u8; 10]) };
+		assert_noop!(
+			TemplateModule::upsert_contact(RuntimeOrigin::signed(1), unknown, bounded(&[2_u8; 10])),
+			Error::<Test>::UnsupportedEnvelope,
+		);
+		// plain envelopes are only left over from before envelopes
+		let plain = EncryptedEnvelope::plain(bounded(b"Bob"));
+		assert_noop!(
+			TemplateModule::upsert_contact(RuntimeOrigin::signed(1), plain, bounded(&[2_u8; 10])),
+			Error::<Test>::UnsupportedEnvelope,
+		);
+	})
+}
+
+#[test]
+fn contacts_are_synced_in_batches() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::batch_upsert_contacts(
+			RuntimeOrigin::signed(1),
+			batch(&[
+				(envelope(&[4_u8; 10]), bounded(&[1_u8; 10])),
+				(envelope(&[4_u8; 10]), bounded(&[2_u8; 10])),
+				(envelope(&[4_u8; 10]), bounded(&[3_u8; 10])),
+			])
+		));
+		assert_eq!(TemplateModule::get_contact_by_account_id(1, bounded(&[2_u8; 10])).deposit, 30);
+		assert_eq!(Balances::reserved_balance(1), 90);
+
+		// a failing entry reverts the whole batch
+		assert_noop!(
+			TemplateModule::batch_upsert_contacts(
+				RuntimeOrigin::signed(1),
+				batch(&[
+					(envelope(&[5_u8; 10]), bounded(&[1_u8; 10])),
+					(
+						EncryptedEnvelope { algorithm: 7, ..envelope(&[5_u8; 10]) },
+						bounded(&[4_u8; 10])
+					),
+				])
+			),
+			Error::<Test>::UnsupportedEnvelope,
+		);
+
+		assert_ok!(TemplateModule::batch_remove_contacts(
+			RuntimeOrigin::signed(1),
+			batch(&[bounded(&[1_u8; 10]), bounded(&[9_u8; 10])])
+		));
+		assert!(!ContactByAccountIdStore::<Test>::contains_key(1, bounded(&[1_u8; 10])));
+		assert_eq!(Balances::reserved_balance(1), 60);
+	})
+}
+
+#[test]
+fn clear_contacts_across_several_calls() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for i in 1..=4 {
+			assert_ok!(TemplateModule::upsert_contact(
+				RuntimeOrigin::signed(1),
+				envelope(&[4_u8; 10]),
+				bounded(&[i; 10])
+			));
+		}
+		assert_ok!(TemplateModule::set_contact_account(
+			RuntimeOrigin::signed(1),
+			bounded(&[2_u8; 10]),
+			Some(2)
+		));
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(2),
+			envelope(&[4_u8; 10]),
+			bounded(&[1_u8; 10])
+		));
+
+		// the mock clears at most 3 contacts per call
+		assert_eq!(
+			crate::Call::<Test>::clear_contacts { limit: u32::MAX }
+				.get_dispatch_info()
+				.weight,
+			<() as crate::WeightInfo>::clear_contacts(3)
+		);
+		assert_ok!(TemplateModule::clear_contacts(RuntimeOrigin::signed(1), u32::MAX));
+		System::assert_last_event(
+			Event::ContactsCleared { who: 1, removed: 3, complete: false }.into(),
+		);
+
+		assert_ok!(TemplateModule::clear_contacts(RuntimeOrigin::signed(1), 2));
+		System::assert_last_event(
+			Event::ContactsCleared { who: 1, removed: 1, complete: true }.into(),
+		);
+
+		assert_eq!(ContactByAccountIdStore::<Test>::iter_prefix(1).count(), 0);
+		assert_eq!(TemplateModule::get_contact_by_linked_account(1, 2), None);
+		assert_eq!(Balances::reserved_balance(1), 0);
+		// contacts of other accounts are kept
+		assert_eq!(Balances::reserved_balance(2), 30);
+	})
+}
+
+#[test]
+fn contact_count_is_limited() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for i in 1..=4 {
+			assert_ok!(TemplateModule::upsert_contact(
+				RuntimeOrigin::signed(1),
+				envelope(&[4_u8; 10]),
+				bounded(&[i; 10])
+			));
+		}
+		assert_eq!(TemplateModule::get_contact_count(1), 4);
+		assert_noop!(
+			TemplateModule::upsert_contact(
+				RuntimeOrigin::signed(1),
+				envelope(&[4_u8; 10]),
+				bounded(&[5_u8; 10])
+			),
+			Error::<Test>::TooManyContacts,
+		);
+
+		// overwriting an existing contact is not counted
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[5_u8; 10]),
+			bounded(&[4_u8; 10])
+		));
+		assert_eq!(TemplateModule::get_contact_count(1), 4);
+
+		// neither is removing a contact which doesn't exist
+		assert_ok!(TemplateModule::remove_contact(RuntimeOrigin::signed(1), bounded(&[9_u8; 10])));
+		assert_eq!(TemplateModule::get_contact_count(1), 4);
+
+		assert_ok!(TemplateModule::remove_contact(RuntimeOrigin::signed(1), bounded(&[4_u8; 10])));
+		assert_eq!(TemplateModule::get_contact_count(1), 3);
+
+		// batches count new contacts only and stop at the limit as a whole
+		assert_noop!(
+			TemplateModule::batch_upsert_contacts(
+				RuntimeOrigin::signed(1),
+				batch(&[
+					(envelope(&[4_u8; 10]), bounded(&[1_u8; 10])),
+					(envelope(&[4_u8; 10]), bounded(&[5_u8; 10])),
+					(envelope(&[4_u8; 10]), bounded(&[6_u8; 10])),
+				])
+			),
+			Error::<Test>::TooManyContacts,
+		);
+		assert_ok!(TemplateModule::batch_upsert_contacts(
+			RuntimeOrigin::signed(1),
+			batch(&[
+				(envelope(&[4_u8; 10]), bounded(&[1_u8; 10])),
+				(envelope(&[4_u8; 10]), bounded(&[5_u8; 10])),
+			])
+		));
+		assert_eq!(TemplateModule::get_contact_count(1), 4);
+
+		assert_ok!(TemplateModule::batch_remove_contacts(
+			RuntimeOrigin::signed(1),
+			batch(&[bounded(&[1_u8; 10]), bounded(&[1_u8; 10])])
+		));
+		assert_eq!(TemplateModule::get_contact_count(1), 3);
+
+		assert_ok!(TemplateModule::clear_contacts(RuntimeOrigin::signed(1), 10));
+		assert_eq!(TemplateModule::get_contact_count(1), 0);
+		assert!(!ContactCount::<Test>::contains_key(1));
+	})
+}
+
+#[test]
+fn contacts_are_sorted_into_groups() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let address = bounded(&[1_u8; 10]);
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 10]),
+			address.clone()
+		));
+
+		assert_noop!(
+			TemplateModule::create_contact_group(
+				RuntimeOrigin::signed(1),
+				EncryptedEnvelope::plain(bounded(&[1_u8; 5]))
+			),
+			Error::<Test>::UnsupportedEnvelope,
+		);
+		for group_id in 0..2 {
+			assert_ok!(TemplateModule::create_contact_group(
+				RuntimeOrigin::signed(1),
+				envelope(&[1_u8; 5])
+			));
+			System::assert_last_event(Event::ContactGroupCreated { who: 1, group_id }.into());
+		}
+		assert_noop!(
+			TemplateModule::create_contact_group(RuntimeOrigin::signed(1), envelope(&[1_u8; 5])),
+			Error::<Test>::TooManyContactGroups,
+		);
+		// contact and both encoded group names
+		assert_eq!(Balances::reserved_balance(1), 30 + 113);
+
+		assert_ok!(TemplateModule::rename_contact_group(
+			RuntimeOrigin::signed(1),
+			1,
+			envelope(&[2_u8; 5])
+		));
+		assert_eq!(
+			TemplateModule::get_contact_groups(1).groups.get(&1),
+			Some(&envelope(&[2_u8; 5]))
+		);
+
+		assert_noop!(
+			TemplateModule::set_contact_metadata(
+				RuntimeOrigin::signed(1),
+				address.clone(),
+				batch(&[2]),
+				0,
+				None
+			),
+			Error::<Test>::ContactGroupNotFound,
+		);
+		assert_ok!(TemplateModule::set_contact_metadata(
+			RuntimeOrigin::signed(1),
+			address.clone(),
+			batch(&[1, 0]),
+			CONTACT_FAVOURITE,
+			Some(envelope(&[7_u8; 5]))
+		));
+		// group ids and the note are part of the contact deposit
+		assert_eq!(TemplateModule::get_contact_by_account_id(1, address.clone()).deposit, 43);
+
+		// renaming keeps the metadata
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[5_u8; 10]),
+			address.clone()
+		));
+		let contact = TemplateModule::get_contact_by_account_id(1, address.clone());
+		assert_eq!(contact.groups.into_inner(), vec![0, 1]);
+		assert!(contact.is_favourite());
+
+		// ids of removed groups are not handed out again
+		assert_ok!(TemplateModule::remove_contact_group(RuntimeOrigin::signed(1), 0));
+		System::assert_last_event(Event::ContactGroupRemoved { who: 1, group_id: 0 }.into());
+		assert_ok!(TemplateModule::create_contact_group(
+			RuntimeOrigin::signed(1),
+			envelope(&[1_u8; 5])
+		));
+		System::assert_last_event(Event::ContactGroupCreated { who: 1, group_id: 2 }.into());
+		assert_noop!(
+			TemplateModule::remove_contact_group(RuntimeOrigin::signed(1), 0),
+			Error::<Test>::ContactGroupNotFound,
+		);
+
+		assert_ok!(TemplateModule::remove_contact_group(RuntimeOrigin::signed(1), 1));
+		assert_ok!(TemplateModule::remove_contact_group(RuntimeOrigin::signed(1), 2));
+		assert_eq!(Balances::reserved_balance(1), 43);
+	})
+}
+
+#[test]
+fn set_contact_account_links_contact() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let address = bounded(&[1_u8; 100]);
+
+		assert_noop!(
+			TemplateModule::set_contact_account(RuntimeOrigin::signed(1), address.clone(), Some(2)),
+			Error::<Test>::ContactNotFound,
+		);
+
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 100]),
+			address.clone()
+		));
+		assert_ok!(TemplateModule::set_contact_account(
+			RuntimeOrigin::signed(1),
+			address.clone(),
+			Some(2)
+		));
+		assert_eq!(TemplateModule::get_contact_by_linked_account(1, 2), Some(address.clone()));
+		// the linked account is part of the deposit
+		assert_eq!(Balances::reserved_balance(1), 218);
+
+		// renaming keeps the link
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 50]),
+			address.clone()
+		));
+		assert_eq!(TemplateModule::get_contact_by_account_id(1, address.clone()).account, Some(2));
+		assert_eq!(Balances::reserved_balance(1), 168);
+
+		// an account can be linked to a single contact only
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 50]),
+			bounded(&[2_u8; 100])
+		));
+		assert_noop!(
+			TemplateModule::set_contact_account(
+				RuntimeOrigin::signed(1),
+				bounded(&[2_u8; 100]),
+				Some(2)
+			),
+			Error::<Test>::ContactAccountTaken,
+		);
+
+		assert_ok!(TemplateModule::remove_contact(RuntimeOrigin::signed(1), address));
+		assert_eq!(TemplateModule::get_contact_by_linked_account(1, 2), None);
+		assert_ok!(TemplateModule::set_contact_account(
+			RuntimeOrigin::signed(1),
+			bounded(&[2_u8; 100]),
+			Some(2)
+		));
+	})
+}
+
+#[test]
+fn upsert_contact_requires_deposit() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// account 5 has no funds in the mock
+		assert_noop!(
+			TemplateModule::upsert_contact(
+				RuntimeOrigin::signed(5),
+				envelope(&[4_u8; 100]),
+				bounded(&[1_u8; 100])
+			),
+			pallet_balances::Error::<Test>::InsufficientBalance,
+		);
 	})
 }
 
@@ -73,18 +529,23 @@ fn test_register_account_id_is_already_registered() {
 		System::set_block_number(1);
 
 		let sender = RuntimeOrigin::signed(1);
-		let nickname = [123_u8; 21];
-		let address = [1_u8; 32];
+		let nickname = bounded(&[b'a'; 21]);
+		let address = address(1);
 		let sender_addr = ensure_signed(sender.clone()).unwrap();
 
-		assert_ok!(TemplateModule::register(sender.clone(), nickname.clone(), address));
+		assert_ok!(TemplateModule::register(
+			sender.clone(),
+			nickname.clone(),
+			address,
+			key_proof(1, 1)
+		));
 
-		let addr_resp = TemplateModule::get_address_by_nickname(nickname);
+		let addr_resp = TemplateModule::get_address_by_nickname(nickname.clone());
 
 		assert_eq!(sender_addr, addr_resp.unwrap());
 
 		assert_noop!(
-			TemplateModule::register(sender, nickname, address),
+			TemplateModule::register(sender, nickname, address, key_proof(1, 1)),
 			Error::<Test>::AccountIdAlreadyRegistered,
 		);
 	})
@@ -100,79 +561,1899 @@ fn test_register_nickname_is_already_registered() {
 
 		let sender_addr = ensure_signed(sender.clone()).unwrap();
 
-		let nickname = [123_u8; 21];
-		let address = [1_u8; 32];
+		let nickname = bounded(&[b'a'; 21]);
+		let address = address(1);
 
-		assert_ok!(TemplateModule::register(sender, nickname.clone(), address));
+		assert_ok!(TemplateModule::register(sender, nickname.clone(), address, key_proof(1, 1)));
 
-		let addr_resp = TemplateModule::get_address_by_nickname(nickname);
+		let addr_resp = TemplateModule::get_address_by_nickname(nickname.clone());
 
 		assert_eq!(sender_addr, addr_resp.unwrap());
 
 		assert_noop!(
-			TemplateModule::register(sender2, nickname, address),
+			TemplateModule::register(sender2, nickname, address, key_proof(2, 1)),
 			Error::<Test>::NicknameAlreadyRegistered,
 		);
 	})
 }
 
 #[test]
-fn offer_chat_with_static_values() {
+fn register_normalizes_nickname() {
 	new_test_ext().execute_with(|| {
-		// Go past genesis block so events get deposited
 		System::set_block_number(1);
 
-		let sender = RuntimeOrigin::signed(1);
-		let receiver = RuntimeOrigin::signed(2);
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"Alice_1"),
+			address(1),
+			key_proof(1, 1)
+		));
 
-		let offer = [3u8; 2048];
-		let welcome_msg = [1u8; 300];
+		assert_eq!(TemplateModule::get_address_by_nickname(bounded(b"alice_1")), Some(1));
+		assert_eq!(TemplateModule::get_address_by_account_id(1).nickname, bounded(b"alice_1"));
 
-		let sender_account_id = ensure_signed(sender.clone()).expect("cant get account id");
-		let receiver_account_id = ensure_signed(receiver).expect("cant get account id");
+		// mixed case duplicates are rejected
+		assert_noop!(
+			TemplateModule::register(
+				RuntimeOrigin::signed(2),
+				bounded(b"ALICE_1"),
+				address(2),
+				key_proof(2, 2)
+			),
+			Error::<Test>::NicknameAlreadyRegistered,
+		);
+	})
+}
 
-		assert_ok!(TemplateModule::offer_chat(
-			sender,
-			welcome_msg.clone(),
-			offer.clone(),
-			receiver_account_id,
+#[test]
+fn register_invalid_nickname() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// too short, zero bytes, spaces, control bytes and non ASCII letters
+		let nicknames: [&[u8]; 5] = [b"al", &[0_u8; 21], b"al ice", b"al\nice", "ålice".as_bytes()];
+
+		for nickname in nicknames {
+			assert_noop!(
+				TemplateModule::register(
+					RuntimeOrigin::signed(1),
+					bounded(nickname),
+					address(1),
+					key_proof(1, 1)
+				),
+				Error::<Test>::InvalidNickname,
+			);
+		}
+	})
+}
+
+#[test]
+fn reserved_nickname_can_not_be_claimed() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_noop!(
+			TemplateModule::reserve_nickname(RuntimeOrigin::signed(1), bounded(b"admin")),
+			DispatchError::BadOrigin,
+		);
+
+		assert_ok!(TemplateModule::reserve_nickname(RuntimeOrigin::root(), bounded(b"Admin")));
+		System::assert_last_event(Event::NicknameReserved { nickname: bounded(b"admin") }.into());
+
+		assert_noop!(
+			TemplateModule::register(
+				RuntimeOrigin::signed(1),
+				bounded(b"ADMIN"),
+				address(1),
+				key_proof(1, 1)
+			),
+			Error::<Test>::NicknameReserved,
+		);
+
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_noop!(
+			TemplateModule::change_nickname(RuntimeOrigin::signed(1), bounded(b"admin")),
+			Error::<Test>::NicknameReserved,
+		);
+
+		assert_ok!(TemplateModule::unreserve_nickname(RuntimeOrigin::root(), bounded(b"admin")));
+		assert_ok!(TemplateModule::change_nickname(RuntimeOrigin::signed(1), bounded(b"admin")));
+	})
+}
+
+#[test]
+fn unregister_releases_nickname() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let nickname = bounded(b"alice");
+
+		assert_noop!(
+			TemplateModule::unregister(RuntimeOrigin::signed(1)),
+			Error::<Test>::NotRegistered,
+		);
+
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			nickname.clone(),
+			address(1),
+			key_proof(1, 1)
 		));
+		assert_ok!(TemplateModule::unregister(RuntimeOrigin::signed(1)));
 
 		System::assert_last_event(
-			Event::Offer {
-				offer,
-				offered_by: sender_account_id,
-				offered_to: receiver_account_id,
-				welcome_msg,
-			}
-			.into(),
-		)
-	});
+			Event::Unregistered { who: 1, nickname: nickname.clone() }.into(),
+		);
+		assert_eq!(TemplateModule::get_address_by_nickname(nickname.clone()), None);
+		assert_eq!(TemplateModule::get_address_by_account_id(1), ItemByAccountId::default());
+		assert_eq!(Balances::reserved_balance(1), 0);
+
+		// the nickname can be claimed again
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(2),
+			nickname.clone(),
+			address(2),
+			key_proof(2, 2)
+		));
+		assert_eq!(TemplateModule::get_address_by_nickname(nickname), Some(2));
+	})
 }
 
 #[test]
-fn answer_chat_with_static_values() {
+fn change_nickname_frees_old_one() {
 	new_test_ext().execute_with(|| {
-		// Go past genesis block so events get deposited
 		System::set_block_number(1);
 
-		let sender = RuntimeOrigin::signed(1);
-		let receiver = RuntimeOrigin::signed(2);
+		let old = bounded(b"alice");
+		let new = bounded(b"alice_in_wonderland");
 
-		let answer = [3u8; 2048];
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			old.clone(),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(2),
+			bounded(b"bob"),
+			address(2),
+			key_proof(2, 2)
+		));
 
-		let sender_account_id = ensure_signed(sender.clone()).expect("cant get account id");
-		let receiver_account_id = ensure_signed(receiver).expect("cant get account id");
+		assert_noop!(
+			TemplateModule::change_nickname(RuntimeOrigin::signed(1), bounded(b"bob")),
+			Error::<Test>::NicknameAlreadyRegistered,
+		);
+		assert_noop!(
+			TemplateModule::change_nickname(RuntimeOrigin::signed(3), new.clone()),
+			Error::<Test>::NotRegistered,
+		);
 
-		assert_ok!(TemplateModule::answer_chat(sender, answer.clone(), receiver_account_id,));
+		assert_ok!(TemplateModule::change_nickname(RuntimeOrigin::signed(1), new.clone()));
 
 		System::assert_last_event(
-			Event::Answer {
-				answer,
-				answer_from: sender_account_id,
+			Event::NicknameChanged { who: 1, old: old.clone(), new: new.clone() }.into(),
+		);
+		assert_eq!(TemplateModule::get_address_by_nickname(old), None);
+		assert_eq!(TemplateModule::get_address_by_nickname(new.clone()), Some(1));
+		assert_eq!(
+			TemplateModule::get_address_by_account_id(1),
+			ItemByAccountId { address: address(1), nickname: new, deposit: 61 }
+		);
+		assert_eq!(Balances::reserved_balance(1), 61);
+	})
+}
+
+#[test]
+fn transfer_nickname_requires_acceptance() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let nickname = bounded(b"alice");
+
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			nickname.clone(),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_ok!(TemplateModule::transfer_nickname(RuntimeOrigin::signed(1), 2));
+
+		System::assert_last_event(
+			Event::NicknameTransferProposed { from: 1, to: 2, nickname: nickname.clone() }.into(),
+		);
+
+		// only the proposed receiver can accept
+		assert_noop!(
+			TemplateModule::accept_nickname(
+				RuntimeOrigin::signed(3),
+				1,
+				address(3),
+				key_proof(3, 3)
+			),
+			Error::<Test>::NoPendingTransfer,
+		);
+
+		assert_ok!(TemplateModule::accept_nickname(
+			RuntimeOrigin::signed(2),
+			1,
+			address(2),
+			key_proof(2, 2)
+		));
+
+		System::assert_last_event(
+			Event::NicknameTransferred { from: 1, to: 2, nickname: nickname.clone() }.into(),
+		);
+		assert_eq!(TemplateModule::get_address_by_nickname(nickname.clone()), Some(2));
+		assert_eq!(
+			TemplateModule::get_address_by_account_id(2),
+			ItemByAccountId { address: address(2), nickname, deposit: 47 }
+		);
+		assert_eq!(TemplateModule::get_address_by_account_id(1), ItemByAccountId::default());
+		assert_eq!(TemplateModule::get_pending_nickname_transfer(1), None);
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_eq!(Balances::reserved_balance(2), 47);
+
+		// the transfer can't be accepted twice
+		assert_noop!(
+			TemplateModule::accept_nickname(
+				RuntimeOrigin::signed(2),
+				1,
+				address(2),
+				key_proof(2, 2)
+			),
+			Error::<Test>::NoPendingTransfer,
+		);
+	})
+}
+
+#[test]
+fn pending_nickname_transfers_can_be_withdrawn() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		for (who, nickname) in [(1, b"alice"), (2, b"carol"), (3, b"david")] {
+			assert_ok!(TemplateModule::register(
+				RuntimeOrigin::signed(who),
+				bounded(nickname),
+				address(who as u8),
+				key_proof(who, who as u8)
+			));
+		}
+
+		// nothing to cancel yet
+		assert_noop!(
+			TemplateModule::cancel_nickname_transfer(RuntimeOrigin::signed(1)),
+			Error::<Test>::NoPendingTransfer,
+		);
+
+		// cancelled explicitly
+		assert_ok!(TemplateModule::transfer_nickname(RuntimeOrigin::signed(1), 4));
+		assert_ok!(TemplateModule::cancel_nickname_transfer(RuntimeOrigin::signed(1)));
+		System::assert_last_event(Event::NicknameTransferCancelled { from: 1, to: 4 }.into());
+		assert_eq!(TemplateModule::get_pending_nickname_transfer(1), None);
+		assert_noop!(
+			TemplateModule::accept_nickname(
+				RuntimeOrigin::signed(4),
+				1,
+				address(4),
+				key_proof(4, 4)
+			),
+			Error::<Test>::NoPendingTransfer,
+		);
+
+		// dropped when the nickname changes
+		assert_ok!(TemplateModule::transfer_nickname(RuntimeOrigin::signed(2), 4));
+		assert_ok!(TemplateModule::change_nickname(RuntimeOrigin::signed(2), bounded(b"bob")));
+		System::assert_has_event(Event::NicknameTransferCancelled { from: 2, to: 4 }.into());
+		assert_eq!(TemplateModule::get_pending_nickname_transfer(2), None);
+		assert_noop!(
+			TemplateModule::accept_nickname(
+				RuntimeOrigin::signed(4),
+				2,
+				address(4),
+				key_proof(4, 4)
+			),
+			Error::<Test>::NoPendingTransfer,
+		);
+
+		// dropped on unregister
+		assert_ok!(TemplateModule::transfer_nickname(RuntimeOrigin::signed(3), 4));
+		assert_ok!(TemplateModule::unregister(RuntimeOrigin::signed(3)));
+		assert_eq!(TemplateModule::get_pending_nickname_transfer(3), None);
+		assert_noop!(
+			TemplateModule::accept_nickname(
+				RuntimeOrigin::signed(4),
+				3,
+				address(4),
+				key_proof(4, 4)
+			),
+			Error::<Test>::NoPendingTransfer,
+		);
+	})
+}
+
+#[test]
+fn devices_get_their_own_messaging_keys() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_noop!(
+			TemplateModule::add_device(
+				RuntimeOrigin::signed(1),
+				address(2),
+				bounded(b"phone"),
+				key_proof(1, 2)
+			),
+			Error::<Test>::NotRegistered,
+		);
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+
+		// the registered key and keys of other devices can't be added again
+		assert_noop!(
+			TemplateModule::add_device(
+				RuntimeOrigin::signed(1),
+				address(1),
+				bounded(b"phone"),
+				key_proof(1, 1)
+			),
+			Error::<Test>::DeviceKeyTaken,
+		);
+		assert_noop!(
+			TemplateModule::add_device(
+				RuntimeOrigin::signed(1),
+				address(2),
+				bounded(b"phone"),
+				key_proof(1, 3)
+			),
+			Error::<Test>::InvalidKeyProof,
+		);
+		assert_ok!(TemplateModule::add_device(
+			RuntimeOrigin::signed(1),
+			address(2),
+			bounded(b"phone"),
+			key_proof(1, 2)
+		));
+		System::assert_last_event(Event::DeviceAdded { who: 1, device_id: 0 }.into());
+		assert_eq!(
+			TemplateModule::get_devices(1).into_inner(),
+			vec![Device {
+				id: 0,
+				public_key: address(2),
+				label: bounded(b"phone"),
+				added_at: 1,
+				deposit: 47
+			}]
+		);
+		// registration and device
+		assert_eq!(Balances::reserved_balance(1), 47 + 47);
+		assert_noop!(
+			TemplateModule::add_device(
+				RuntimeOrigin::signed(1),
+				address(2),
+				bounded(b"tablet"),
+				key_proof(1, 2)
+			),
+			Error::<Test>::DeviceKeyTaken,
+		);
+		assert_noop!(
+			TemplateModule::update_address(RuntimeOrigin::signed(1), address(2), key_proof(1, 2)),
+			Error::<Test>::DeviceKeyTaken,
+		);
+
+		assert_ok!(TemplateModule::add_device(
+			RuntimeOrigin::signed(1),
+			address(3),
+			bounded(b"laptop"),
+			key_proof(1, 3)
+		));
+		assert_noop!(
+			TemplateModule::add_device(
+				RuntimeOrigin::signed(1),
+				address(4),
+				bounded(b"tablet"),
+				key_proof(1, 4)
+			),
+			Error::<Test>::TooManyDevices,
+		);
+
+		// ids of removed devices are not handed out again
+		assert_ok!(TemplateModule::remove_device(RuntimeOrigin::signed(1), 0));
+		System::assert_last_event(Event::DeviceRemoved { who: 1, device_id: 0 }.into());
+		assert_noop!(
+			TemplateModule::remove_device(RuntimeOrigin::signed(1), 0),
+			Error::<Test>::DeviceNotFound,
+		);
+		assert_ok!(TemplateModule::add_device(
+			RuntimeOrigin::signed(1),
+			address(4),
+			bounded(b"tablet"),
+			key_proof(1, 4)
+		));
+		System::assert_last_event(Event::DeviceAdded { who: 1, device_id: 2 }.into());
+
+		// devices go along with the registration
+		assert_ok!(TemplateModule::unregister(RuntimeOrigin::signed(1)));
+		assert!(TemplateModule::get_devices(1).is_empty());
+		assert_eq!(Balances::reserved_balance(1), 0);
+	})
+}
+
+#[test]
+fn offers_can_target_a_device() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_ok!(TemplateModule::add_device(
+			RuntimeOrigin::signed(1),
+			address(2),
+			bounded(b"phone"),
+			key_proof(1, 2)
+		));
+
+		assert_noop!(
+			TemplateModule::offer_chat(
+				RuntimeOrigin::signed(2),
+				bounded(&[1u8; 3]),
+				bounded(&[2u8; 3]),
+				1,
+				None,
+				Some(1)
+			),
+			Error::<Test>::DeviceNotFound,
+		);
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(2),
+			bounded(&[1u8; 3]),
+			bounded(&[2u8; 3]),
+			1,
+			None,
+			Some(0)
+		));
+		System::assert_last_event(
+			Event::Offer {
+				offer: bounded(&[2u8; 3]),
+				offered_by: 2,
+				offered_to: 1,
+				welcome_msg: bounded(&[1u8; 3]),
+				session_id: 0,
+				room: None,
+				device: Some(0),
+			}
+			.into(),
+		);
+		assert_eq!(TemplateModule::get_chat_session((2, 1, 0)).and_then(|s| s.device), Some(0));
+
+		// without a device the offer is for all of them
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(2),
+			bounded(&[1u8; 3]),
+			bounded(&[2u8; 3]),
+			1,
+			None,
+			None
+		));
+		assert_eq!(TemplateModule::get_chat_session((2, 1, 1)).and_then(|s| s.device), None);
+	})
+}
+
+#[test]
+fn messaging_key_has_to_sign_binding() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// signed by another key or for another account
+		assert_noop!(
+			TemplateModule::register(
+				RuntimeOrigin::signed(1),
+				bounded(b"alice"),
+				address(1),
+				key_proof(1, 2)
+			),
+			Error::<Test>::InvalidKeyProof,
+		);
+		assert_noop!(
+			TemplateModule::register(
+				RuntimeOrigin::signed(1),
+				bounded(b"alice"),
+				address(1),
+				key_proof(2, 1)
+			),
+			Error::<Test>::InvalidKeyProof,
+		);
+
+		let proof = key_proof(1, 1);
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			proof
+		));
+		assert_eq!(TemplateModule::get_key_binding_nonce(1), 1);
+
+		// proofs can't be replayed once the nonce moved on
+		assert_ok!(TemplateModule::update_address(
+			RuntimeOrigin::signed(1),
+			address(2),
+			key_proof(1, 2)
+		));
+		assert_noop!(
+			TemplateModule::update_address(RuntimeOrigin::signed(1), address(1), proof),
+			Error::<Test>::InvalidKeyProof,
+		);
+	})
+}
+
+#[test]
+fn update_address_keeps_history() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_noop!(
+			TemplateModule::update_address(RuntimeOrigin::signed(1), address(2), key_proof(1, 2)),
+			Error::<Test>::NotRegistered,
+		);
+
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_ok!(TemplateModule::update_address(
+			RuntimeOrigin::signed(1),
+			address(2),
+			key_proof(1, 2)
+		));
+
+		System::assert_last_event(
+			Event::AddressUpdated { who: 1, old: address(1), new: address(2) }.into(),
+		);
+		assert_eq!(TemplateModule::get_address_by_account_id(1).address, address(2));
+
+		System::set_block_number(2);
+		assert_ok!(TemplateModule::update_address(
+			RuntimeOrigin::signed(1),
+			address(3),
+			key_proof(1, 3)
+		));
+		System::set_block_number(3);
+		assert_ok!(TemplateModule::update_address(
+			RuntimeOrigin::signed(1),
+			address(4),
+			key_proof(1, 4)
+		));
+
+		// only the last 2 addresses are kept in the mock
+		assert_eq!(
+			TemplateModule::get_address_history(1).into_inner(),
+			vec![(address(2), 2), (address(3), 3)]
+		);
+	})
+}
+
+#[test]
+fn offer_chat_with_static_values() {
+	new_test_ext().execute_with(|| {
+		// Go past genesis block so events get deposited
+		System::set_block_number(1);
+
+		let sender = RuntimeOrigin::signed(1);
+		let receiver = RuntimeOrigin::signed(2);
+
+		let offer = bounded(&[3u8; 2048]);
+		let welcome_msg = bounded(&[1u8; 300]);
+
+		let sender_account_id = ensure_signed(sender.clone()).expect("cant get account id");
+		let receiver_account_id = ensure_signed(receiver).expect("cant get account id");
+
+		assert_ok!(TemplateModule::offer_chat(
+			sender,
+			welcome_msg.clone(),
+			offer.clone(),
+			receiver_account_id,
+			None,
+			None,
+		));
+
+		System::assert_last_event(
+			Event::Offer {
+				offer: offer.clone(),
+				offered_by: sender_account_id,
+				offered_to: receiver_account_id,
+				welcome_msg: welcome_msg.clone(),
+				session_id: 0,
+				room: None,
+				device: None,
+			}
+			.into(),
+		);
+
+		assert_eq!(
+			TemplateModule::get_chat_session((sender_account_id, receiver_account_id, 0)),
+			Some(ChatSession {
+				offer,
+				welcome_msg,
+				state: SessionState::Pending,
+				created_at: 1,
+				room: None,
+				device: None
+			})
+		);
+	});
+}
+
+#[test]
+fn answer_chat_with_static_values() {
+	new_test_ext().execute_with(|| {
+		// Go past genesis block so events get deposited
+		System::set_block_number(1);
+
+		let sender = RuntimeOrigin::signed(1);
+		let receiver = RuntimeOrigin::signed(2);
+
+		let answer = bounded(&[3u8; 2048]);
+
+		let sender_account_id = ensure_signed(sender.clone()).expect("cant get account id");
+		let receiver_account_id = ensure_signed(receiver.clone()).expect("cant get account id");
+
+		assert_ok!(TemplateModule::offer_chat(
+			receiver,
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			sender_account_id,
+			None,
+			None,
+		));
+
+		assert_ok!(TemplateModule::answer_chat(sender, answer.clone(), receiver_account_id, 0));
+
+		System::assert_last_event(
+			Event::Answer {
+				answer,
+				answer_from: sender_account_id,
 				answer_to: receiver_account_id,
+				session_id: 0,
 			}
 			.into(),
-		)
+		);
+
+		assert_eq!(
+			TemplateModule::get_chat_session((receiver_account_id, sender_account_id, 0))
+				.map(|session| session.state),
+			Some(SessionState::Answered)
+		);
+	});
+}
+
+#[test]
+fn answer_chat_requires_pending_offer() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let answer = bounded(&[3u8; 2048]);
+
+		// nothing was offered yet
+		assert_noop!(
+			TemplateModule::answer_chat(RuntimeOrigin::signed(1), answer.clone(), 2, 0),
+			Error::<Test>::NoPendingOffer,
+		);
+
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(2),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			1,
+			None,
+			None
+		));
+
+		// only the offeree can answer
+		assert_noop!(
+			TemplateModule::answer_chat(RuntimeOrigin::signed(3), answer.clone(), 2, 0),
+			Error::<Test>::NoPendingOffer,
+		);
+
+		assert_ok!(TemplateModule::answer_chat(RuntimeOrigin::signed(1), answer.clone(), 2, 0));
+
+		// an offer can be answered only once
+		assert_noop!(
+			TemplateModule::answer_chat(RuntimeOrigin::signed(1), answer, 2, 0),
+			Error::<Test>::NoPendingOffer,
+		);
+	});
+}
+
+#[test]
+fn reject_chat_records_the_rejection() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			2,
+			None,
+			None
+		));
+
+		// only the receiver can decline
+		assert_noop!(
+			TemplateModule::reject_chat(RuntimeOrigin::signed(3), 1, 0, 7),
+			Error::<Test>::NoPendingOffer,
+		);
+
+		assert_ok!(TemplateModule::reject_chat(RuntimeOrigin::signed(2), 1, 0, 7));
+		System::assert_last_event(
+			Event::OfferRejected { offered_by: 1, offered_to: 2, session_id: 0, reason_code: 7 }
+				.into(),
+		);
+		assert_eq!(
+			TemplateModule::get_chat_session((1, 2, 0)).map(|session| session.state),
+			Some(SessionState::Rejected)
+		);
+		assert!(TemplateModule::pending_offers(&2).is_empty());
+
+		assert_noop!(
+			TemplateModule::answer_chat(RuntimeOrigin::signed(2), bounded(&[3u8; 2048]), 1, 0),
+			Error::<Test>::NoPendingOffer,
+		);
+		assert_noop!(
+			TemplateModule::reject_chat(RuntimeOrigin::signed(2), 1, 0, 7),
+			Error::<Test>::NoPendingOffer,
+		);
+
+		// a rejected session doesn't expire but is removed once it comes due
+		System::set_block_number(11);
+		TemplateModule::on_initialize(11);
+		assert!(!System::events().iter().any(|record| matches!(
+			record.event,
+			RuntimeEvent::TemplateModule(Event::OfferExpired { .. })
+		)));
+		assert_eq!(
+			TemplateModule::get_chat_session((1, 2, 0)).map(|session| session.state),
+			Some(SessionState::Rejected)
+		);
+		TemplateModule::on_idle(11, Weight::MAX);
+		assert_eq!(TemplateModule::get_chat_session((1, 2, 0)), None);
+	});
+}
+
+#[test]
+fn cancel_offer_removes_pending_session() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			2,
+			None,
+			None
+		));
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			3,
+			None,
+			None
+		));
+		assert_ok!(TemplateModule::answer_chat(
+			RuntimeOrigin::signed(3),
+			bounded(&[3u8; 2048]),
+			1,
+			1
+		));
+
+		// answered offers can't be withdrawn anymore
+		assert_noop!(
+			TemplateModule::cancel_offer(RuntimeOrigin::signed(1), 3, 1),
+			Error::<Test>::NoPendingOffer,
+		);
+		assert_noop!(
+			TemplateModule::cancel_offer(RuntimeOrigin::signed(2), 1, 0),
+			Error::<Test>::NoPendingOffer,
+		);
+
+		assert_ok!(TemplateModule::cancel_offer(RuntimeOrigin::signed(1), 2, 0));
+		System::assert_last_event(
+			Event::OfferCancelled { offered_by: 1, offered_to: 2, session_id: 0 }.into(),
+		);
+		assert_eq!(TemplateModule::get_chat_session((1, 2, 0)), None);
+		assert!(TemplateModule::pending_offers(&2).is_empty());
+		assert!(TemplateModule::pending_offers(&3).is_empty());
+	});
+}
+
+#[test]
+fn ice_candidates_are_trickled_until_expiry() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			2,
+			None,
+			None
+		));
+
+		// both sides can trickle candidates while the offer is pending and once it is answered
+		assert_ok!(TemplateModule::add_ice_candidates(
+			RuntimeOrigin::signed(1),
+			2,
+			0,
+			bounded(&[5u8; 64])
+		));
+		System::assert_last_event(
+			Event::IceCandidates { from: 1, to: 2, session_id: 0, candidates: bounded(&[5u8; 64]) }
+				.into(),
+		);
+		assert_ok!(TemplateModule::answer_chat(
+			RuntimeOrigin::signed(2),
+			bounded(&[3u8; 2048]),
+			1,
+			0
+		));
+		assert_ok!(TemplateModule::add_ice_candidates(
+			RuntimeOrigin::signed(2),
+			1,
+			0,
+			bounded(&[6u8; 64])
+		));
+
+		// only the two parties of the session can use it
+		assert_noop!(
+			TemplateModule::add_ice_candidates(RuntimeOrigin::signed(3), 1, 0, bounded(&[7u8; 64])),
+			Error::<Test>::NoActiveSession,
+		);
+
+		System::set_block_number(11);
+		TemplateModule::on_initialize(11);
+		TemplateModule::on_idle(11, Weight::MAX);
+
+		assert_noop!(
+			TemplateModule::add_ice_candidates(RuntimeOrigin::signed(1), 2, 0, bounded(&[5u8; 64])),
+			Error::<Test>::NoActiveSession,
+		);
+	});
+}
+
+#[test]
+fn blocked_sender_can_not_signal() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			2,
+			None,
+			None
+		));
+
+		assert_ok!(TemplateModule::block_account(RuntimeOrigin::signed(1), 2));
+		assert_ok!(TemplateModule::block_account(RuntimeOrigin::signed(2), 1));
+		// blocking the same account twice reserves a single deposit
+		assert_ok!(TemplateModule::block_account(RuntimeOrigin::signed(2), 1));
+		assert_eq!(Balances::reserved_balance(2), 18);
+
+		assert_noop!(
+			TemplateModule::offer_chat(
+				RuntimeOrigin::signed(1),
+				bounded(&[1u8; 300]),
+				bounded(&[2u8; 2048]),
+				2,
+				None,
+				None
+			),
+			Error::<Test>::Blocked,
+		);
+		assert_noop!(
+			TemplateModule::answer_chat(RuntimeOrigin::signed(2), bounded(&[3u8; 2048]), 1, 0),
+			Error::<Test>::Blocked,
+		);
+		assert_noop!(
+			TemplateModule::add_ice_candidates(RuntimeOrigin::signed(1), 2, 0, bounded(&[5u8; 64])),
+			Error::<Test>::Blocked,
+		);
+
+		// other accounts are not affected
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(3),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			2,
+			None,
+			None
+		));
+
+		assert_ok!(TemplateModule::unblock_account(RuntimeOrigin::signed(1), 2));
+		assert_ok!(TemplateModule::answer_chat(
+			RuntimeOrigin::signed(2),
+			bounded(&[3u8; 2048]),
+			1,
+			0
+		));
+		assert_noop!(
+			TemplateModule::unblock_account(RuntimeOrigin::signed(1), 2),
+			Error::<Test>::NotBlocked,
+		);
+
+		assert_ok!(TemplateModule::unblock_account(RuntimeOrigin::signed(2), 1));
+		assert_eq!(Balances::reserved_balance(2), 0);
+	});
+}
+
+#[test]
+fn inbound_policy_restricts_offers() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let offer = |from: u64| {
+			TemplateModule::offer_chat(
+				RuntimeOrigin::signed(from),
+				bounded(&[1u8; 300]),
+				bounded(&[2u8; 2048]),
+				1,
+				None,
+				None,
+			)
+		};
+
+		assert_ok!(TemplateModule::set_inbound_policy(
+			RuntimeOrigin::signed(1),
+			InboundPolicy::Nobody
+		));
+		System::assert_last_event(
+			Event::InboundPolicySet { who: 1, policy: InboundPolicy::Nobody }.into(),
+		);
+		assert_noop!(offer(2), Error::<Test>::OfferNotAccepted);
+
+		assert_ok!(TemplateModule::set_inbound_policy(
+			RuntimeOrigin::signed(1),
+			InboundPolicy::ContactsOnly
+		));
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4_u8; 10]),
+			bounded(&[2_u8; 10])
+		));
+		assert_ok!(TemplateModule::set_contact_account(
+			RuntimeOrigin::signed(1),
+			bounded(&[2_u8; 10]),
+			Some(2)
+		));
+		assert_ok!(offer(2));
+		assert_noop!(offer(3), Error::<Test>::OfferNotAccepted);
+
+		assert_ok!(TemplateModule::set_inbound_policy(
+			RuntimeOrigin::signed(1),
+			InboundPolicy::Everyone
+		));
+		assert_eq!(TemplateModule::get_inbound_policy(1), InboundPolicy::Everyone);
+		assert_ok!(offer(3));
+	});
+}
+
+#[test]
+fn room_membership_is_managed_by_admins() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::create_room(RuntimeOrigin::signed(1), bounded(b"friends")));
+		System::assert_last_event(
+			Event::RoomCreated { room_id: 0, creator: 1, name: bounded(b"friends") }.into(),
+		);
+		// name deposit and member deposit
+		assert_eq!(Balances::reserved_balance(1), 17 + 18);
+
+		assert_noop!(TemplateModule::join(RuntimeOrigin::signed(2), 0), Error::<Test>::NotInvited);
+		assert_noop!(
+			TemplateModule::invite(RuntimeOrigin::signed(2), 0, 3),
+			Error::<Test>::NotRoomAdmin
+		);
+
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 2));
+		System::assert_last_event(Event::MemberInvited { room_id: 0, by: 1, who: 2 }.into());
+		assert_ok!(TemplateModule::join(RuntimeOrigin::signed(2), 0));
+		System::assert_has_event(Event::MemberJoined { room_id: 0, who: 2 }.into());
+		assert_eq!(Balances::reserved_balance(2), 18);
+		assert_noop!(
+			TemplateModule::invite(RuntimeOrigin::signed(1), 0, 2),
+			Error::<Test>::AlreadyRoomMember
+		);
+
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 3));
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 4));
+		assert_ok!(TemplateModule::join(RuntimeOrigin::signed(3), 0));
+		assert_noop!(TemplateModule::join(RuntimeOrigin::signed(4), 0), Error::<Test>::RoomFull);
+
+		// the only admin can neither step down nor leave while others remain
+		assert_noop!(
+			TemplateModule::set_admin(RuntimeOrigin::signed(1), 0, 1, false),
+			Error::<Test>::LastAdmin
+		);
+		assert_noop!(TemplateModule::leave(RuntimeOrigin::signed(1), 0), Error::<Test>::LastAdmin);
+
+		assert_ok!(TemplateModule::set_admin(RuntimeOrigin::signed(1), 0, 2, true));
+		System::assert_last_event(
+			Event::RoomRoleChanged { room_id: 0, who: 2, role: RoomRole::Admin }.into(),
+		);
+		assert_ok!(TemplateModule::kick(RuntimeOrigin::signed(2), 0, 3));
+		System::assert_has_event(Event::MemberKicked { room_id: 0, by: 2, who: 3 }.into());
+		assert_eq!(Balances::reserved_balance(3), 0);
+		assert_noop!(
+			TemplateModule::kick(RuntimeOrigin::signed(2), 0, 3),
+			Error::<Test>::NotRoomMember
+		);
+
+		assert_ok!(TemplateModule::leave(RuntimeOrigin::signed(1), 0));
+		System::assert_has_event(Event::MemberLeft { room_id: 0, who: 1 }.into());
+		// the creator keeps the name deposit reserved until the room is closed
+		assert_eq!(Balances::reserved_balance(1), 17);
+
+		assert_ok!(TemplateModule::leave(RuntimeOrigin::signed(2), 0));
+		System::assert_has_event(Event::RoomClosed { room_id: 0 }.into());
+		assert_eq!(TemplateModule::get_room(0), None);
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_eq!(Balances::reserved_balance(2), 0);
+	});
+}
+
+#[test]
+fn room_invites_can_be_revoked_and_declined() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::create_room(RuntimeOrigin::signed(1), bounded(b"friends")));
+		for who in [2, 3, 4] {
+			assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, who));
+		}
+		// the mock allows 3 pending invites
+		assert_noop!(
+			TemplateModule::invite(RuntimeOrigin::signed(1), 0, 5),
+			Error::<Test>::RoomFull
+		);
+
+		// inviting again changes nothing
+		let events = System::events().len();
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 2));
+		assert_eq!(System::events().len(), events);
+
+		assert_noop!(
+			TemplateModule::revoke_invite(RuntimeOrigin::signed(2), 0, 3),
+			Error::<Test>::NotRoomAdmin
+		);
+		assert_ok!(TemplateModule::revoke_invite(RuntimeOrigin::signed(1), 0, 2));
+		System::assert_last_event(Event::InviteRevoked { room_id: 0, by: 1, who: 2 }.into());
+		assert_noop!(
+			TemplateModule::revoke_invite(RuntimeOrigin::signed(1), 0, 2),
+			Error::<Test>::NotInvited
+		);
+		assert_noop!(TemplateModule::join(RuntimeOrigin::signed(2), 0), Error::<Test>::NotInvited);
+
+		assert_ok!(TemplateModule::decline_invite(RuntimeOrigin::signed(3), 0));
+		System::assert_last_event(Event::InviteDeclined { room_id: 0, who: 3 }.into());
+		assert_noop!(
+			TemplateModule::decline_invite(RuntimeOrigin::signed(3), 0),
+			Error::<Test>::NotInvited
+		);
+		assert_noop!(
+			TemplateModule::decline_invite(RuntimeOrigin::signed(3), 1),
+			Error::<Test>::RoomNotFound
+		);
+
+		// the freed places can be given out again
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 5));
+		assert_eq!(TemplateModule::get_room(0).unwrap().invited.into_inner(), vec![4, 5]);
+	});
+}
+
+#[test]
+fn offers_within_a_room_need_membership() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let offer = |from: u64, to: u64, room: Option<u64>| {
+			TemplateModule::offer_chat(
+				RuntimeOrigin::signed(from),
+				bounded(&[1u8; 300]),
+				bounded(&[2u8; 2048]),
+				to,
+				room,
+				None,
+			)
+		};
+
+		assert_ok!(TemplateModule::create_room(RuntimeOrigin::signed(1), bounded(b"team")));
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 2));
+		assert_ok!(TemplateModule::join(RuntimeOrigin::signed(2), 0));
+		assert_ok!(TemplateModule::set_inbound_policy(
+			RuntimeOrigin::signed(2),
+			InboundPolicy::Nobody
+		));
+
+		assert_noop!(offer(1, 2, None), Error::<Test>::OfferNotAccepted);
+		assert_noop!(offer(1, 2, Some(1)), Error::<Test>::RoomNotFound);
+		assert_noop!(offer(1, 3, Some(0)), Error::<Test>::NotRoomMember);
+		assert_noop!(offer(3, 1, Some(0)), Error::<Test>::NotRoomMember);
+
+		// members accept offers from each other within the room
+		assert_ok!(offer(1, 2, Some(0)));
+		assert_eq!(
+			TemplateModule::get_chat_session((1, 2, 0)).map(|session| session.room),
+			Some(Some(0))
+		);
+
+		assert_ok!(TemplateModule::block_account(RuntimeOrigin::signed(2), 1));
+		assert_noop!(offer(1, 2, Some(0)), Error::<Test>::Blocked);
+	});
+}
+
+fn room_keys(members: &[u64]) -> RoomKeyCopies<Test> {
+	let keys: BTreeMap<_, _> =
+		members.iter().map(|member| (*member, bounded(&[1u8; 32]))).collect();
+	keys.try_into().unwrap()
+}
+
+#[test]
+fn room_key_rotates_on_membership_change() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::create_room(RuntimeOrigin::signed(1), bounded(b"team")));
+		assert_eq!(TemplateModule::get_room_key_epoch(0), 0);
+		assert_ok!(TemplateModule::set_room_key(RuntimeOrigin::signed(1), 0, 0, room_keys(&[1])));
+		System::assert_last_event(Event::RoomKeySet { room_id: 0, epoch: 0, by: 1 }.into());
+		// room and member deposits plus the deposit for the key copies
+		assert_eq!(Balances::reserved_balance(1), 32 + 52);
+
+		assert_ok!(TemplateModule::invite(RuntimeOrigin::signed(1), 0, 2));
+		assert_ok!(TemplateModule::join(RuntimeOrigin::signed(2), 0));
+		System::assert_last_event(Event::RoomKeyRotated { room_id: 0, epoch: 1 }.into());
+		assert_eq!(TemplateModule::get_room_key_epoch(0), 1);
+		assert_eq!(TemplateModule::get_room_key(0), None);
+		assert_eq!(Balances::reserved_balance(1), 32);
+
+		assert_noop!(
+			TemplateModule::set_room_key(RuntimeOrigin::signed(1), 0, 0, room_keys(&[1, 2])),
+			Error::<Test>::StaleRoomKeyEpoch
+		);
+		assert_noop!(
+			TemplateModule::set_room_key(RuntimeOrigin::signed(1), 0, 1, room_keys(&[1])),
+			Error::<Test>::RoomKeyMembersMismatch
+		);
+		assert_noop!(
+			TemplateModule::set_room_key(RuntimeOrigin::signed(1), 0, 1, room_keys(&[1, 3])),
+			Error::<Test>::RoomKeyMembersMismatch
+		);
+		assert_noop!(
+			TemplateModule::set_room_key(RuntimeOrigin::signed(2), 0, 1, room_keys(&[1, 2])),
+			Error::<Test>::NotRoomAdmin
+		);
+
+		assert_ok!(TemplateModule::set_room_key(
+			RuntimeOrigin::signed(1),
+			0,
+			1,
+			room_keys(&[1, 2])
+		));
+		assert_eq!(
+			TemplateModule::get_room_key(0).map(|room_key| room_key.keys),
+			Some(room_keys(&[1, 2]))
+		);
+
+		// changing roles keeps the epoch
+		assert_ok!(TemplateModule::set_admin(RuntimeOrigin::signed(1), 0, 2, true));
+		assert_eq!(TemplateModule::get_room_key_epoch(0), 1);
+
+		assert_ok!(TemplateModule::kick(RuntimeOrigin::signed(2), 0, 1));
+		System::assert_last_event(Event::RoomKeyRotated { room_id: 0, epoch: 2 }.into());
+		assert_eq!(TemplateModule::get_room_key(0), None);
+
+		assert_ok!(TemplateModule::leave(RuntimeOrigin::signed(2), 0));
+		System::assert_last_event(Event::RoomClosed { room_id: 0 }.into());
+		assert!(!RoomKeyEpochs::<Test>::contains_key(0));
+	});
+}
+
+#[test]
+fn one_time_prekeys_are_claimed_once() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		let signed_prekey = signed_prekey(1, [7_u8; 32]);
+		let publish = |prekeys: &[[u8; 32]]| {
+			TemplateModule::publish_prekeys(
+				RuntimeOrigin::signed(1),
+				signed_prekey.clone(),
+				prekeys.to_vec().try_into().unwrap(),
+			)
+		};
+
+		assert_noop!(publish(&[[1_u8; 32]]), Error::<Test>::NotRegistered);
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_ok!(publish(&[[1_u8; 32], [2_u8; 32]]));
+		System::assert_last_event(Event::PrekeysPublished { who: 1, one_time_prekeys: 2 }.into());
+		// registration deposit plus the deposit for the bundle
+		assert_eq!(Balances::reserved_balance(1), 47 + 171);
+		assert_noop!(publish(&[[3_u8; 32], [4_u8; 32]]), Error::<Test>::TooManyPrekeys);
+
+		assert_ok!(TemplateModule::claim_one_time_prekey(RuntimeOrigin::signed(2), 1));
+		System::assert_has_event(
+			Event::OneTimePrekeyClaimed { by: 2, target: 1, prekey: Some([1_u8; 32]) }.into(),
+		);
+		System::assert_last_event(Event::PrekeysLow { who: 1, remaining: 1 }.into());
+
+		assert_ok!(TemplateModule::claim_one_time_prekey(RuntimeOrigin::signed(3), 1));
+		System::assert_has_event(
+			Event::OneTimePrekeyClaimed { by: 3, target: 1, prekey: Some([2_u8; 32]) }.into(),
+		);
+		// only the signed prekey is left
+		assert_ok!(TemplateModule::claim_one_time_prekey(RuntimeOrigin::signed(2), 1));
+		System::assert_has_event(
+			Event::OneTimePrekeyClaimed { by: 2, target: 1, prekey: None }.into(),
+		);
+		assert_eq!(
+			TemplateModule::get_prekey_bundle(1).map(|bundle| bundle.signed_prekey),
+			Some(signed_prekey.clone())
+		);
+		assert_eq!(Balances::reserved_balance(1), 47 + 107);
+
+		assert_noop!(
+			TemplateModule::claim_one_time_prekey(RuntimeOrigin::signed(1), 3),
+			Error::<Test>::NoPrekeys
+		);
+
+		// prekeys signed by the old identity key are dropped
+		assert_ok!(TemplateModule::update_address(
+			RuntimeOrigin::signed(1),
+			address(2),
+			key_proof(1, 2)
+		));
+		assert_eq!(TemplateModule::get_prekey_bundle(1), None);
+		assert_eq!(Balances::reserved_balance(1), 47);
+	});
+}
+
+#[test]
+fn signed_prekeys_are_checked_against_the_address() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+		let publish = |seed: u8| {
+			TemplateModule::publish_prekeys(
+				RuntimeOrigin::signed(1),
+				signed_prekey(seed, [7_u8; 32]),
+				batch(&[[1_u8; 32]]),
+			)
+		};
+
+		assert_noop!(publish(2), Error::<Test>::InvalidPrekeySignature);
+		assert_noop!(
+			TemplateModule::publish_prekeys(
+				RuntimeOrigin::signed(1),
+				SignedPrekey { key: [7_u8; 32], signature: [8_u8; 64] },
+				batch(&[[1_u8; 32]]),
+			),
+			Error::<Test>::InvalidPrekeySignature
+		);
+		assert_ok!(publish(1));
+
+		// prekeys signed by the old identity key aren't accepted anymore
+		assert_ok!(TemplateModule::update_address(
+			RuntimeOrigin::signed(1),
+			address(2),
+			key_proof(1, 2)
+		));
+		assert_noop!(publish(1), Error::<Test>::InvalidPrekeySignature);
+		assert_ok!(publish(2));
+	});
+}
+
+#[test]
+fn offers_are_rate_limited() {
+	new_test_ext().execute_with(|| {
+		let offer = || {
+			TemplateModule::offer_chat(
+				RuntimeOrigin::signed(1),
+				bounded(&[1u8; 300]),
+				bounded(&[2u8; 2048]),
+				2,
+				None,
+				None,
+			)
+		};
+
+		// the mock allows 5 offers per 10 blocks and 2 expiries per block
+		for block in [1, 1, 2, 2, 3] {
+			System::set_block_number(block);
+			assert_ok!(offer());
+		}
+		assert!(TemplateModule::is_offer_rate_limited(&1));
+		assert_noop!(offer(), Error::<Test>::RateLimited);
+
+		let call = RuntimeCall::TemplateModule(crate::Call::offer_chat {
+			welcome_msg: bounded(&[1u8; 300]),
+			offer: bounded(&[2u8; 2048]),
+			to: 2,
+			room: None,
+			device: None,
+		});
+		assert_eq!(
+			CheckOfferRateLimit::<Test>::new().validate(&1, &call, &Default::default(), 0),
+			Err(InvalidTransaction::Custom(RATE_LIMITED).into())
+		);
+		assert_ok!(CheckOfferRateLimit::<Test>::new().validate(&2, &call, &Default::default(), 0));
+
+		// a new window starts once the old one is over
+		System::set_block_number(11);
+		assert!(!TemplateModule::is_offer_rate_limited(&1));
+		assert_ok!(CheckOfferRateLimit::<Test>::new().validate(&1, &call, &Default::default(), 0));
+		assert_ok!(offer());
+	});
+}
+
+#[test]
+fn rate_limits_are_removed_once_their_window_is_over() {
+	new_test_ext().execute_with(|| {
+		let offer = |from, to| {
+			TemplateModule::offer_chat(
+				RuntimeOrigin::signed(from),
+				bounded(&[1u8; 300]),
+				bounded(&[2u8; 2048]),
+				to,
+				None,
+				None,
+			)
+		};
+
+		System::set_block_number(1);
+		assert_ok!(offer(1, 3));
+		System::set_block_number(5);
+		assert_ok!(offer(2, 3));
+
+		// the window of 1 lasts until block 11
+		TemplateModule::on_idle(10, Weight::MAX);
+		assert!(crate::OfferRateLimits::<Test>::contains_key(1));
+
+		System::set_block_number(11);
+		TemplateModule::on_idle(11, Weight::MAX);
+		assert!(!crate::OfferRateLimits::<Test>::contains_key(1));
+		assert!(crate::OfferRateLimits::<Test>::contains_key(2));
+
+		// a new window is queued on its own
+		assert_ok!(offer(1, 3));
+		System::set_block_number(15);
+		TemplateModule::on_idle(15, Weight::MAX);
+		assert!(crate::OfferRateLimits::<Test>::contains_key(1));
+		assert!(!crate::OfferRateLimits::<Test>::contains_key(2));
+		assert_eq!(crate::RateLimitQueueBounds::<Test>::get(), (2, 3));
+	});
+}
+
+// searches a proof of work of `who` over its next account nonce and the hash of `block_number`
+// meeting the mock difficulty
+fn feeless_proof(who: u64, block_number: u64, valid: bool) -> FeelessProof<u64> {
+	let account_nonce = System::account_nonce(who);
+	let block_hash = System::block_hash(block_number);
+	let difficulty = <Test as crate::Config>::FeelessPowDifficulty::get();
+
+	let nonce = (0..)
+		.find(|nonce: &u64| {
+			let work = (who, account_nonce, block_hash, *nonce).using_encoded(blake2_256);
+			(work[0] >> (8 - difficulty) == 0) == valid
+		})
+		.unwrap();
+
+	FeelessProof { block_number, nonce }
+}
+
+#[test]
+fn feeless_signals_need_proof_of_work() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(2);
+		frame_system::BlockHash::<Test>::insert(1, H256::repeat_byte(1));
+
+		let offer = RuntimeCall::TemplateModule(crate::Call::offer_chat {
+			welcome_msg: bounded(&[1u8; 300]),
+			offer: bounded(&[2u8; 2048]),
+			to: 2,
+			room: None,
+			device: None,
+		});
+		let extension =
+			|proof| ChargeOrFeeless::<Test, _>::new(CheckOfferRateLimit::<Test>::new(), proof);
+		let info = Default::default();
+
+		assert_ok!(extension(Some(feeless_proof(1, 1, true))).validate(&1, &offer, &info, 0));
+		assert_eq!(
+			extension(Some(feeless_proof(1, 1, false))).validate(&1, &offer, &info, 0),
+			Err(InvalidTransaction::Custom(INSUFFICIENT_POW).into())
+		);
+		assert_eq!(
+			extension(Some(FeelessProof { block_number: 5, nonce: 0 }))
+				.validate(&1, &offer, &info, 0),
+			Err(InvalidTransaction::Custom(UNKNOWN_POW_BLOCK).into())
+		);
+
+		// only chat signals can be feeless
+		let other = RuntimeCall::TemplateModule(crate::Call::unregister {});
+		assert_eq!(
+			extension(Some(feeless_proof(1, 1, true))).validate(&1, &other, &info, 0),
+			Err(InvalidTransaction::Call.into())
+		);
+
+		// the mock allows 2 feeless calls per era
+		for _ in 0..2 {
+			let proof = feeless_proof(1, 1, true);
+			// as `CheckNonce` does before
+			System::inc_account_nonce(1);
+			assert_ok!(extension(Some(proof)).pre_dispatch(&1, &offer, &info, 0));
+		}
+		assert_eq!(TemplateModule::feeless_calls_left(&1), 0);
+		assert_eq!(
+			extension(Some(feeless_proof(1, 1, true))).validate(&1, &offer, &info, 0),
+			Err(InvalidTransaction::Custom(FEELESS_QUOTA_EXHAUSTED).into())
+		);
+		// calls without a proof are up to the wrapped extension
+		assert_ok!(extension(None).validate(&1, &offer, &info, 0));
+
+		System::set_block_number(100);
+		assert_eq!(TemplateModule::feeless_calls_left(&1), 2);
+	});
+}
+
+#[test]
+fn feeless_proofs_cannot_be_replayed() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(2);
+		frame_system::BlockHash::<Test>::insert(1, H256::repeat_byte(1));
+
+		let answer = RuntimeCall::TemplateModule(crate::Call::answer_chat {
+			answer: bounded(&[2u8; 64]),
+			to: 2,
+			session_id: 0,
+		});
+		let extension =
+			|proof| ChargeOrFeeless::<Test, _>::new(CheckOfferRateLimit::<Test>::new(), proof);
+		let info = Default::default();
+
+		let proof = feeless_proof(1, 1, true);
+		assert_ok!(extension(Some(proof.clone())).validate(&1, &answer, &info, 0));
+		System::inc_account_nonce(1);
+		assert_ok!(extension(Some(proof.clone())).pre_dispatch(&1, &answer, &info, 0));
+
+		// the proof was bound to the previous account nonce
+		assert_eq!(
+			extension(Some(proof.clone())).validate(&1, &answer, &info, 0),
+			Err(InvalidTransaction::Custom(INSUFFICIENT_POW).into())
+		);
+		System::inc_account_nonce(1);
+		assert_eq!(
+			extension(Some(proof)).pre_dispatch(&1, &answer, &info, 0),
+			Err(InvalidTransaction::Custom(INSUFFICIENT_POW).into())
+		);
+
+		// the genesis hash is never pruned, so it can't back a proof
+		let genesis = feeless_proof(1, 0, true);
+		assert_eq!(
+			extension(Some(genesis)).validate(&1, &answer, &info, 0),
+			Err(InvalidTransaction::Custom(UNKNOWN_POW_BLOCK).into())
+		);
+
+		// neither can a block older than the proof lifetime whose hash is still around
+		let proof = feeless_proof(1, 1, true);
+		assert_ok!(extension(Some(proof.clone())).validate(&1, &answer, &info, 0));
+		System::set_block_number(22);
+		assert_eq!(
+			extension(Some(proof)).validate(&1, &answer, &info, 0),
+			Err(InvalidTransaction::Custom(UNKNOWN_POW_BLOCK).into())
+		);
+	});
+}
+
+#[test]
+fn feeless_calls_are_capped_per_era_and_forgotten_after_it() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(2);
+		frame_system::BlockHash::<Test>::insert(1, H256::repeat_byte(1));
+
+		let answer = RuntimeCall::TemplateModule(crate::Call::answer_chat {
+			answer: bounded(&[2u8; 64]),
+			to: 9,
+			session_id: 0,
+		});
+		let extension =
+			|proof| ChargeOrFeeless::<Test, _>::new(CheckOfferRateLimit::<Test>::new(), proof);
+		let info = Default::default();
+
+		// the mock allows 3 feeless calls per era across all accounts
+		for who in [1, 1, 2] {
+			let proof = feeless_proof(who, 1, true);
+			System::inc_account_nonce(who);
+			assert_ok!(extension(Some(proof)).pre_dispatch(&who, &answer, &info, 0));
+		}
+		assert_eq!(TemplateModule::feeless_calls_left(&3), 2);
+		assert_eq!(TemplateModule::feeless_capacity_left(), 0);
+		assert_eq!(
+			extension(Some(feeless_proof(3, 1, true))).validate(&3, &answer, &info, 0),
+			Err(InvalidTransaction::Custom(FEELESS_CAPACITY_EXHAUSTED).into())
+		);
+		assert_eq!(crate::FeelessUsage::<Test>::get(0, 1), 2);
+		assert_eq!(crate::FeelessUsage::<Test>::get(0, 2), 1);
+
+		// the next era starts over and drops the usage of the last one
+		System::set_block_number(100);
+		TemplateModule::on_initialize(100);
+		assert_eq!(crate::FeelessUsage::<Test>::iter_prefix(0).count(), 0);
+		assert_eq!(crate::FeelessCalls::<Test>::get(), (1, 0));
+		assert_eq!(TemplateModule::feeless_capacity_left(), 3);
+		assert_eq!(TemplateModule::feeless_calls_left(&1), 2);
+	});
+}
+
+#[test]
+fn unanswered_offer_expires() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			2,
+			None,
+			None
+		));
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(1),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			3,
+			None,
+			None
+		));
+		assert_ok!(TemplateModule::answer_chat(
+			RuntimeOrigin::signed(3),
+			bounded(&[3u8; 2048]),
+			1,
+			1
+		));
+
+		// the offer ttl is 10 blocks in the mock
+		System::set_block_number(11);
+		TemplateModule::on_initialize(11);
+
+		System::assert_last_event(
+			Event::OfferExpired { offered_by: 1, offered_to: 2, session_id: 0 }.into(),
+		);
+		assert_eq!(
+			TemplateModule::get_chat_session((1, 2, 0)).map(|session| session.state),
+			Some(SessionState::Expired)
+		);
+		assert_eq!(
+			TemplateModule::get_chat_session((1, 3, 1)).map(|session| session.state),
+			Some(SessionState::Answered)
+		);
+		assert!(TemplateModule::pending_offers(&2).is_empty());
+
+		// expired sessions are removed with the weight left in the block
+		TemplateModule::on_idle(11, Weight::MAX);
+
+		assert_eq!(TemplateModule::get_chat_session((1, 2, 0)), None);
+		assert_eq!(TemplateModule::get_chat_session((1, 3, 1)), None);
+
+		// an expired offer can not be answered anymore
+		assert_noop!(
+			TemplateModule::answer_chat(RuntimeOrigin::signed(2), bounded(&[3u8; 2048]), 1, 0),
+			Error::<Test>::NoPendingOffer,
+		);
+	});
+}
+
+#[test]
+fn offers_beyond_the_expiries_per_block_expire_in_the_next_one() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+
+		// one account can't use up the expiries of a block for everyone else
+		for to in [2, 3, 4] {
+			assert_ok!(TemplateModule::offer_chat(
+				RuntimeOrigin::signed(1),
+				bounded(&[1u8; 300]),
+				bounded(&[2u8; 2048]),
+				to,
+				None,
+				None
+			));
+		}
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(5),
+			bounded(&[1u8; 300]),
+			bounded(&[2u8; 2048]),
+			6,
+			None,
+			None
+		));
+
+		let state = |key| TemplateModule::get_chat_session(key).map(|session| session.state);
+
+		// at most 2 offers expire in the same block in the mock
+		System::set_block_number(11);
+		TemplateModule::on_initialize(11);
+		assert_eq!(state((1, 2, 0)), Some(SessionState::Expired));
+		assert_eq!(state((1, 3, 1)), Some(SessionState::Expired));
+		assert_eq!(state((1, 4, 2)), Some(SessionState::Pending));
+		assert_eq!(state((5, 6, 3)), Some(SessionState::Pending));
+
+		System::set_block_number(12);
+		TemplateModule::on_initialize(12);
+		assert_eq!(state((1, 4, 2)), Some(SessionState::Expired));
+		assert_eq!(state((5, 6, 3)), Some(SessionState::Expired));
+
+		// nothing is left to expire
+		System::reset_events();
+		TemplateModule::on_initialize(13);
+		assert!(System::events().is_empty());
+	});
+}
+
+#[test]
+fn migrate_from_v0_trims_padding() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(0).put::<TemplateModule>();
+
+		let mut contact_addr = [0_u8; 1000];
+		contact_addr[..3].copy_from_slice(&[7, 0, 7]);
+		let mut contact_name = [0_u8; 1000];
+		contact_name[..2].copy_from_slice(&[4, 4]);
+		let mut nickname = [0_u8; 21];
+		nickname[..5].copy_from_slice(b"alice");
+
+		v1::v0::ContactByAccountIdStore::<Test>::insert(
+			1,
+			contact_addr,
+			v1::v0::ContactByAccountId { name: contact_name },
+		);
+		v1::v0::ItemByNicknameStore::<Test>::insert(nickname, 1);
+		v1::v0::ItemByAccountIdStore::<Test>::insert(
+			1,
+			v1::v0::ItemByAccountId { address: [1_u8; 32], nickname },
+		);
+
+		<(
+			v1::MigrateToV1<Test>,
+			v2::MigrateToV2<Test>,
+			v3::MigrateToV3<Test>,
+			v4::MigrateToV4<Test>,
+			v5::MigrateToV5<Test>,
+			v6::MigrateToV6<Test>,
+			v7::MigrateToV7<Test>,
+			v8::MigrateToV8<Test>,
+			v9::MigrateToV9<Test>,
+			v10::MigrateToV10<Test>,
+			v11::MigrateToV11<Test>,
+		)>::on_runtime_upgrade();
+
+		assert_eq!(TemplateModule::on_chain_storage_version(), 11);
+		assert_eq!(TemplateModule::get_contact_count(1), 1);
+		assert_eq!(
+			TemplateModule::get_contact_by_account_id(1, bounded(&[7, 0, 7])),
+			ContactByAccountId {
+				name: EncryptedEnvelope::plain(bounded(&[4, 4])),
+				..Default::default()
+			}
+		);
+		assert_eq!(TemplateModule::get_address_by_nickname(bounded(b"alice")), Some(1));
+		assert_eq!(
+			TemplateModule::get_address_by_account_id(1),
+			ItemByAccountId { address: [1_u8; 32], nickname: bounded(b"alice"), deposit: 0 }
+		);
+	});
+}
+
+#[test]
+fn migrate_to_v9_indexes_pending_offers() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(8).put::<TemplateModule>();
+
+		let session = |state| ChatSession::<Test> {
+			offer: bounded(&[2u8; 3]),
+			welcome_msg: bounded(&[1u8; 3]),
+			state,
+			created_at: 1,
+			room: None,
+			device: None,
+		};
+		crate::ChatSessions::<Test>::insert((2, 1, 0), session(SessionState::Pending));
+		crate::ChatSessions::<Test>::insert((3, 1, 1), session(SessionState::Answered));
+		crate::ChatSessions::<Test>::insert((1, 2, 2), session(SessionState::Pending));
+
+		v9::MigrateToV9::<Test>::on_runtime_upgrade();
+
+		assert_eq!(TemplateModule::on_chain_storage_version(), 9);
+		assert_eq!(
+			TemplateModule::pending_offers(&1),
+			vec![(2, 0, session(SessionState::Pending))]
+		);
+		assert_eq!(
+			TemplateModule::pending_offers(&2),
+			vec![(1, 2, session(SessionState::Pending))]
+		);
+		assert!(TemplateModule::pending_offers(&3).is_empty());
+	});
+}
+
+#[test]
+fn migrate_to_v10_queues_expiries_in_order() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(9).put::<TemplateModule>();
+
+		let session = ChatSession::<Test> {
+			offer: bounded(&[2u8; 3]),
+			welcome_msg: bounded(&[1u8; 3]),
+			state: SessionState::Pending,
+			created_at: 1,
+			room: None,
+			device: None,
+		};
+		crate::ChatSessions::<Test>::insert((1, 2, 0), session.clone());
+		crate::ChatSessions::<Test>::insert((1, 3, 1), session.clone());
+		crate::ChatSessions::<Test>::insert((2, 3, 2), session);
+		v10::SessionExpiries::<Test>::insert(12, batch(&[(2, 3, 2)]));
+		v10::SessionExpiries::<Test>::insert(11, batch(&[(1, 2, 0), (1, 3, 1)]));
+
+		v10::MigrateToV10::<Test>::on_runtime_upgrade();
+
+		assert_eq!(TemplateModule::on_chain_storage_version(), 10);
+		assert_eq!(v10::SessionExpiries::<Test>::iter_keys().count(), 0);
+
+		let state = |key| TemplateModule::get_chat_session(key).map(|session| session.state);
+		System::set_block_number(11);
+		TemplateModule::on_initialize(11);
+		assert_eq!(state((1, 2, 0)), Some(SessionState::Expired));
+		assert_eq!(state((1, 3, 1)), Some(SessionState::Expired));
+		assert_eq!(state((2, 3, 2)), Some(SessionState::Pending));
+
+		System::set_block_number(12);
+		TemplateModule::on_initialize(12);
+		assert_eq!(state((2, 3, 2)), Some(SessionState::Expired));
+	});
+}
+
+#[test]
+fn migrate_to_v11_normalizes_nicknames() {
+	new_test_ext().execute_with(|| {
+		StorageVersion::new(10).put::<TemplateModule>();
+
+		let register = |who: u64, nickname: &[u8], deposit: u64| {
+			assert_ok!(Balances::reserve(&who, deposit));
+			crate::ItemByNicknameStore::<Test>::insert(
+				bounded::<<Test as crate::Config>::MaxNicknameLen>(nickname),
+				who,
+			);
+			crate::ItemByAccountIdStore::<Test>::insert(
+				who,
+				ItemByAccountId { address: [who as u8; 32], nickname: bounded(nickname), deposit },
+			);
+		};
+		// the one already in normalized form keeps the nickname
+		register(1, b"Alice", 47);
+		register(2, b"alice", 47);
+		// otherwise the lowest account id does
+		register(3, b"CAROL", 0);
+		register(4, b"Carol", 0);
+		register(5, b"Bob", 0);
+		register(6, b"x!", 0);
+		assert_ok!(TemplateModule::add_device(
+			RuntimeOrigin::signed(1),
+			address(7),
+			bounded(b"laptop"),
+			key_proof(1, 7)
+		));
+
+		v11::MigrateToV11::<Test>::on_runtime_upgrade();
+
+		assert_eq!(TemplateModule::on_chain_storage_version(), 11);
+		assert_eq!(TemplateModule::get_address_by_nickname(bounded(b"alice")), Some(2));
+		assert_eq!(TemplateModule::get_address_by_nickname(bounded(b"carol")), Some(3));
+		assert_eq!(TemplateModule::get_address_by_nickname(bounded(b"bob")), Some(5));
+		assert_eq!(TemplateModule::get_address_by_account_id(5).nickname, bounded(b"bob"));
+		assert_eq!(crate::ItemByNicknameStore::<Test>::iter_keys().count(), 3);
+
+		// the others are released with their deposits
+		for who in [1, 4, 6] {
+			assert!(!crate::ItemByAccountIdStore::<Test>::contains_key(who));
+		}
+		assert!(TemplateModule::get_devices(1).is_empty());
+		assert_eq!(Balances::reserved_balance(1), 0);
+		assert_eq!(Balances::reserved_balance(2), 47);
+	});
+}
+
+#[test]
+fn lookups_for_the_runtime_api() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		assert_ok!(TemplateModule::register(
+			RuntimeOrigin::signed(1),
+			bounded(b"Alice"),
+			address(1),
+			key_proof(1, 1)
+		));
+		assert_ok!(TemplateModule::upsert_contact(
+			RuntimeOrigin::signed(1),
+			envelope(&[4, 4]),
+			bounded(&[7, 7])
+		));
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(2),
+			bounded(&[1u8; 3]),
+			bounded(&[2u8; 3]),
+			1,
+			None,
+			None
+		));
+		assert_ok!(TemplateModule::offer_chat(
+			RuntimeOrigin::signed(3),
+			bounded(&[1u8; 3]),
+			bounded(&[2u8; 3]),
+			1,
+			None,
+			None
+		));
+		assert_ok!(TemplateModule::answer_chat(RuntimeOrigin::signed(1), bounded(&[3u8; 3]), 3, 1));
+
+		assert_eq!(TemplateModule::resolve_nickname(b"ALICE"), Some(1));
+		assert_eq!(TemplateModule::resolve_nickname(b"bob"), None);
+		assert_eq!(
+			TemplateModule::reverse_lookup(&1).map(|item| item.nickname),
+			Some(bounded(b"alice"))
+		);
+		assert_eq!(TemplateModule::reverse_lookup(&2), None);
+
+		let contacts = TemplateModule::contacts_of(&1, 0);
+		assert_eq!(contacts.len(), 1);
+		assert_eq!(contacts[0].0, bounded::<<Test as crate::Config>::MaxContactAddrLen>(&[7, 7]));
+		assert!(TemplateModule::contacts_of(&1, 1).is_empty());
+
+		let pending = TemplateModule::pending_offers(&1);
+		assert_eq!(pending.len(), 1);
+		assert_eq!((pending[0].0, pending[0].1), (2, 0));
+		assert!(TemplateModule::pending_offers(&2).is_empty());
 	});
 }
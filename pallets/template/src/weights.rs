@@ -0,0 +1,461 @@
+//! Estimated weights for pallet_template
+//!
+//! These are not benchmark results. The ref times are estimates and the storage accesses were
+//! counted from the dispatchables by hand, so only the `()` implementation is provided and the
+//! runtime uses it until this file is replaced by the output of the benchmark CLI on reference
+//! hardware:
+//!
+//! ./target/release/diffychat benchmark pallet --chain=dev --steps=50 --repeat=20
+//! --pallet=pallet_template --extrinsic=* --execution=wasm --wasm-execution=compiled
+//! --output=pallets/template/src/weights.rs
+
+#![cfg_attr(rustfmt, rustfmt_skip)]
+#![allow(unused_parens)]
+#![allow(unused_imports)]
+
+use frame_support::weights::{Weight, constants::RocksDbWeight};
+
+/// Weight functions needed for pallet_template.
+pub trait WeightInfo {
+	fn offer_chat(o: u32, w: u32, ) -> Weight;
+	fn register(n: u32, ) -> Weight;
+	fn answer_chat(a: u32, ) -> Weight;
+	fn upsert_contact(n: u32, a: u32, ) -> Weight;
+	fn remove_contact(a: u32, ) -> Weight;
+	fn unregister() -> Weight;
+	fn change_nickname(n: u32, ) -> Weight;
+	fn transfer_nickname() -> Weight;
+	fn accept_nickname() -> Weight;
+	fn update_address() -> Weight;
+	fn reserve_nickname(n: u32, ) -> Weight;
+	fn unreserve_nickname(n: u32, ) -> Weight;
+	fn add_ice_candidates(c: u32, ) -> Weight;
+	fn reject_chat() -> Weight;
+	fn cancel_offer() -> Weight;
+	fn block_account() -> Weight;
+	fn unblock_account() -> Weight;
+	fn set_inbound_policy() -> Weight;
+	fn set_contact_account(a: u32, ) -> Weight;
+	fn create_room(n: u32, ) -> Weight;
+	fn invite() -> Weight;
+	fn join() -> Weight;
+	fn leave() -> Weight;
+	fn kick() -> Weight;
+	fn set_admin() -> Weight;
+	fn set_room_key(m: u32, ) -> Weight;
+	fn publish_prekeys(k: u32, ) -> Weight;
+	fn claim_one_time_prekey() -> Weight;
+	fn batch_upsert_contacts(c: u32, ) -> Weight;
+	fn batch_remove_contacts(c: u32, ) -> Weight;
+	fn clear_contacts(c: u32, ) -> Weight;
+	fn create_contact_group() -> Weight;
+	fn rename_contact_group() -> Weight;
+	fn remove_contact_group() -> Weight;
+	fn set_contact_metadata(a: u32, ) -> Weight;
+	fn add_device(l: u32, ) -> Weight;
+	fn remove_device() -> Weight;
+	fn expire_offers(e: u32, ) -> Weight;
+	fn prune_sessions(p: u32, ) -> Weight;
+	fn cancel_nickname_transfer() -> Weight;
+	fn clear_feeless_usage(u: u32, ) -> Weight;
+	fn prune_rate_limits(r: u32, ) -> Weight;
+	fn revoke_invite() -> Weight;
+	fn decline_invite() -> Weight;
+}
+
+/// Estimated weights, see the module docs.
+impl WeightInfo for () {
+	// Accesses TemplateModule BlockedAccounts (r:1 w:0)
+	// Accesses TemplateModule Devices (r:1 w:0)
+	// Accesses TemplateModule Rooms (r:1 w:0)
+	// Accesses TemplateModule InboundPolicies (r:1 w:0)
+	// Accesses TemplateModule ContactIndex (r:1 w:0)
+	// Accesses TemplateModule OfferRateLimits (r:1 w:1)
+	// Accesses TemplateModule NextSessionId (r:1 w:1)
+	// Accesses TemplateModule ExpiryQueueBounds (r:1 w:1)
+	// Accesses TemplateModule ExpiryQueue (r:0 w:1)
+	// Accesses TemplateModule ChatSessions (r:0 w:1)
+	// Accesses TemplateModule PendingOffersTo (r:0 w:1)
+	// Accesses TemplateModule RateLimitQueueBounds (r:1 w:1)
+	// Accesses TemplateModule RateLimitQueue (r:0 w:1)
+	fn offer_chat(o: u32, w: u32, ) -> Weight {
+		Weight::from_ref_time(50_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(o as u64))
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(w as u64))
+			.saturating_add(RocksDbWeight::get().reads(9 as u64))
+			.saturating_add(RocksDbWeight::get().writes(8 as u64))
+	}
+	// Accesses System BlockHash (r:1 w:0)
+	// Accesses TemplateModule KeyBindingNonces (r:1 w:1)
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ReservedNicknames (r:1 w:0)
+	// Accesses TemplateModule ItemByNicknameStore (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn register(n: u32, ) -> Weight {
+		Weight::from_ref_time(86_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(n as u64))
+			.saturating_add(RocksDbWeight::get().reads(6 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	// Accesses TemplateModule BlockedAccounts (r:1 w:0)
+	// Accesses TemplateModule ChatSessions (r:1 w:1)
+	// Accesses TemplateModule PendingOffersTo (r:0 w:1)
+	fn answer_chat(a: u32, ) -> Weight {
+		Weight::from_ref_time(27_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(a as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactCount (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn upsert_contact(n: u32, a: u32, ) -> Weight {
+		Weight::from_ref_time(33_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(n as u64))
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(a as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactCount (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule ContactIndex (r:0 w:1)
+	fn remove_contact(a: u32, ) -> Weight {
+		Weight::from_ref_time(33_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(a as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule ItemByNicknameStore (r:0 w:1)
+	// Accesses TemplateModule PendingNicknameTransfers (r:0 w:1)
+	// Accesses TemplateModule AddressHistory (r:0 w:1)
+	// Accesses TemplateModule PrekeyBundles (r:1 w:1)
+	// Accesses TemplateModule Devices (r:1 w:1)
+	fn unregister() -> Weight {
+		Weight::from_ref_time(52_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(4 as u64))
+			.saturating_add(RocksDbWeight::get().writes(7 as u64))
+	}
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ReservedNicknames (r:1 w:0)
+	// Accesses TemplateModule ItemByNicknameStore (r:1 w:2)
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule PendingNicknameTransfers (r:1 w:1)
+	fn change_nickname(n: u32, ) -> Weight {
+		Weight::from_ref_time(42_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(n as u64))
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:0)
+	// Accesses TemplateModule PendingNicknameTransfers (r:0 w:1)
+	fn transfer_nickname() -> Weight {
+		Weight::from_ref_time(21_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses System BlockHash (r:1 w:0)
+	// Accesses TemplateModule KeyBindingNonces (r:1 w:1)
+	// Accesses TemplateModule PendingNicknameTransfers (r:1 w:1)
+	// Accesses TemplateModule ItemByAccountIdStore (r:2 w:2)
+	// Accesses System Account (r:2 w:2)
+	// Accesses TemplateModule ItemByNicknameStore (r:0 w:1)
+	// Accesses TemplateModule AddressHistory (r:0 w:1)
+	// Accesses TemplateModule PrekeyBundles (r:1 w:1)
+	// Accesses TemplateModule Devices (r:1 w:1)
+	fn accept_nickname() -> Weight {
+		Weight::from_ref_time(109_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(9 as u64))
+			.saturating_add(RocksDbWeight::get().writes(10 as u64))
+	}
+	// Accesses System BlockHash (r:1 w:0)
+	// Accesses TemplateModule KeyBindingNonces (r:1 w:1)
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule Devices (r:1 w:0)
+	// Accesses TemplateModule AddressHistory (r:1 w:1)
+	// Accesses TemplateModule PrekeyBundles (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn update_address() -> Weight {
+		Weight::from_ref_time(80_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(7 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	// Accesses TemplateModule ReservedNicknames (r:0 w:1)
+	fn reserve_nickname(n: u32, ) -> Weight {
+		Weight::from_ref_time(15_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(n as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule ReservedNicknames (r:0 w:1)
+	fn unreserve_nickname(n: u32, ) -> Weight {
+		Weight::from_ref_time(15_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(n as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule BlockedAccounts (r:1 w:0)
+	// Accesses TemplateModule ChatSessions (r:2 w:0)
+	fn add_ice_candidates(c: u32, ) -> Weight {
+		Weight::from_ref_time(25_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(c as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+	}
+	// Accesses TemplateModule ChatSessions (r:1 w:1)
+	// Accesses TemplateModule PendingOffersTo (r:0 w:1)
+	fn reject_chat() -> Weight {
+		Weight::from_ref_time(23_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ChatSessions (r:1 w:1)
+	// Accesses TemplateModule PendingOffersTo (r:0 w:1)
+	fn cancel_offer() -> Weight {
+		Weight::from_ref_time(23_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule BlockedAccounts (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn block_account() -> Weight {
+		Weight::from_ref_time(27_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule BlockedAccounts (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn unblock_account() -> Weight {
+		Weight::from_ref_time(26_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule InboundPolicies (r:0 w:1)
+	fn set_inbound_policy() -> Weight {
+		Weight::from_ref_time(14_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactIndex (r:1 w:2)
+	// Accesses System Account (r:1 w:1)
+	fn set_contact_account(a: u32, ) -> Weight {
+		Weight::from_ref_time(41_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(a as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule NextRoomId (r:1 w:1)
+	// Accesses TemplateModule Rooms (r:0 w:1)
+	fn create_room(n: u32, ) -> Weight {
+		Weight::from_ref_time(32_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(n as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	fn invite() -> Weight {
+		Weight::from_ref_time(24_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	// Accesses System Account (r:2 w:2)
+	// Accesses TemplateModule RoomKeys (r:1 w:1)
+	// Accesses TemplateModule RoomKeyEpochs (r:1 w:1)
+	fn join() -> Weight {
+		Weight::from_ref_time(41_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	// Accesses System Account (r:2 w:2)
+	// Accesses TemplateModule RoomKeys (r:1 w:1)
+	// Accesses TemplateModule RoomKeyEpochs (r:1 w:1)
+	fn leave() -> Weight {
+		Weight::from_ref_time(43_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	// Accesses System Account (r:2 w:2)
+	// Accesses TemplateModule RoomKeys (r:1 w:1)
+	// Accesses TemplateModule RoomKeyEpochs (r:1 w:1)
+	fn kick() -> Weight {
+		Weight::from_ref_time(44_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(5 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	fn set_admin() -> Weight {
+		Weight::from_ref_time(25_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:0)
+	// Accesses TemplateModule RoomKeyEpochs (r:1 w:0)
+	// Accesses System Account (r:2 w:2)
+	// Accesses TemplateModule RoomKeys (r:1 w:1)
+	fn set_room_key(m: u32, ) -> Weight {
+		Weight::from_ref_time(38_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_500_000 as u64).saturating_mul(m as u64))
+			.saturating_add(RocksDbWeight::get().reads(5 as u64))
+			.saturating_add(RocksDbWeight::get().writes(3 as u64))
+	}
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:0)
+	// Accesses TemplateModule PrekeyBundles (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn publish_prekeys(k: u32, ) -> Weight {
+		Weight::from_ref_time(82_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(120_000 as u64).saturating_mul(k as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule PrekeyBundles (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn claim_one_time_prekey() -> Weight {
+		Weight::from_ref_time(31_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactCount (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn batch_upsert_contacts(c: u32, ) -> Weight {
+		Weight::from_ref_time(9_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(36_000_000 as u64).saturating_mul(c as u64))
+			.saturating_add(RocksDbWeight::get().reads((3 as u64).saturating_mul(c as u64)))
+			.saturating_add(RocksDbWeight::get().writes((3 as u64).saturating_mul(c as u64)))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactCount (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule ContactIndex (r:0 w:1)
+	fn batch_remove_contacts(c: u32, ) -> Weight {
+		Weight::from_ref_time(9_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(35_000_000 as u64).saturating_mul(c as u64))
+			.saturating_add(RocksDbWeight::get().reads((3 as u64).saturating_mul(c as u64)))
+			.saturating_add(RocksDbWeight::get().writes((4 as u64).saturating_mul(c as u64)))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactCount (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule ContactIndex (r:0 w:1)
+	fn clear_contacts(c: u32, ) -> Weight {
+		Weight::from_ref_time(14_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(34_000_000 as u64).saturating_mul(c as u64))
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().reads((3 as u64).saturating_mul(c as u64)))
+			.saturating_add(RocksDbWeight::get().writes((4 as u64).saturating_mul(c as u64)))
+	}
+	// Accesses TemplateModule ContactGroupsOf (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn create_contact_group() -> Weight {
+		Weight::from_ref_time(30_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ContactGroupsOf (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn rename_contact_group() -> Weight {
+		Weight::from_ref_time(29_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ContactGroupsOf (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn remove_contact_group() -> Weight {
+		Weight::from_ref_time(28_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ContactByAccountIdStore (r:1 w:1)
+	// Accesses TemplateModule ContactGroupsOf (r:1 w:0)
+	// Accesses System Account (r:1 w:1)
+	fn set_contact_metadata(a: u32, ) -> Weight {
+		Weight::from_ref_time(36_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(2_000 as u64).saturating_mul(a as u64))
+			.saturating_add(RocksDbWeight::get().reads(3 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule ItemByAccountIdStore (r:1 w:0)
+	// Accesses TemplateModule Devices (r:1 w:1)
+	// Accesses System BlockHash (r:1 w:0)
+	// Accesses TemplateModule KeyBindingNonces (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	// Accesses TemplateModule NextDeviceId (r:1 w:1)
+	fn add_device(l: u32, ) -> Weight {
+		Weight::from_ref_time(84_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(1_000 as u64).saturating_mul(l as u64))
+			.saturating_add(RocksDbWeight::get().reads(6 as u64))
+			.saturating_add(RocksDbWeight::get().writes(4 as u64))
+	}
+	// Accesses TemplateModule Devices (r:1 w:1)
+	// Accesses System Account (r:1 w:1)
+	fn remove_device() -> Weight {
+		Weight::from_ref_time(31_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().writes(2 as u64))
+	}
+	// Accesses TemplateModule FeelessCalls (r:1 w:0)
+	// Accesses TemplateModule ExpiryQueueBounds (r:1 w:1)
+	// Accesses TemplateModule ExpiryQueue (r:1 w:1)
+	// Accesses TemplateModule ChatSessions (r:1 w:1)
+	// Accesses TemplateModule PendingOffersTo (r:0 w:1)
+	// Accesses TemplateModule PruneQueueBounds (r:1 w:1)
+	// Accesses TemplateModule PruneQueue (r:0 w:1)
+	fn expire_offers(e: u32, ) -> Weight {
+		Weight::from_ref_time(5_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(24_000_000 as u64).saturating_mul(e as u64))
+			.saturating_add(RocksDbWeight::get().reads(2 as u64))
+			.saturating_add(RocksDbWeight::get().reads((3 as u64).saturating_mul(e as u64)))
+			.saturating_add(RocksDbWeight::get().writes((5 as u64).saturating_mul(e as u64)))
+	}
+	// Accesses TemplateModule PruneQueueBounds (r:1 w:1)
+	// Accesses TemplateModule PruneQueue (r:1 w:1)
+	// Accesses TemplateModule ChatSessions (r:0 w:1)
+	fn prune_sessions(p: u32, ) -> Weight {
+		Weight::from_ref_time(3_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(9_000_000 as u64).saturating_mul(p as u64))
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().reads((1 as u64).saturating_mul(p as u64)))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes((2 as u64).saturating_mul(p as u64)))
+	}
+	// Accesses TemplateModule PendingNicknameTransfers (r:1 w:1)
+	fn cancel_nickname_transfer() -> Weight {
+		Weight::from_ref_time(17_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule FeelessCalls (r:0 w:1)
+	// Accesses TemplateModule FeelessUsage (r:0 w:1)
+	fn clear_feeless_usage(u: u32, ) -> Weight {
+		Weight::from_ref_time(6_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(900_000 as u64).saturating_mul(u as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes((1 as u64).saturating_mul(u as u64)))
+	}
+	// Accesses TemplateModule RateLimitQueueBounds (r:1 w:1)
+	// Accesses TemplateModule RateLimitQueue (r:1 w:1)
+	// Accesses TemplateModule OfferRateLimits (r:1 w:1)
+	fn prune_rate_limits(r: u32, ) -> Weight {
+		Weight::from_ref_time(3_000_000 as u64)
+			.saturating_add(Weight::from_ref_time(12_000_000 as u64).saturating_mul(r as u64))
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().reads((2 as u64).saturating_mul(r as u64)))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes((2 as u64).saturating_mul(r as u64)))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	fn revoke_invite() -> Weight {
+		Weight::from_ref_time(22_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+	// Accesses TemplateModule Rooms (r:1 w:1)
+	fn decline_invite() -> Weight {
+		Weight::from_ref_time(21_000_000 as u64)
+			.saturating_add(RocksDbWeight::get().reads(1 as u64))
+			.saturating_add(RocksDbWeight::get().writes(1 as u64))
+	}
+}
@@ -105,10 +105,10 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
-	transaction_version: 1,
+	transaction_version: 2,
 	state_version: 1,
 };
 
@@ -243,6 +243,17 @@ impl pallet_timestamp::Config for Runtime {
 /// Existential deposit.
 pub const EXISTENTIAL_DEPOSIT: u128 = 500;
 
+/// One unit of the native token. Fees with `IdentityFee` are in the order of a thousandth of it.
+pub const UNIT: Balance = 1_000_000_000_000;
+pub const MILLIUNIT: Balance = UNIT / 1_000;
+pub const MICROUNIT: Balance = MILLIUNIT / 1_000;
+
+/// Deposit for keeping `items` storage entries of `bytes` bytes in total, well above the fee of
+/// the call writing them so that state can't be bloated cheaply.
+pub const fn deposit(items: u32, bytes: u32) -> Balance {
+	items as Balance * 20 * MILLIUNIT + (bytes as Balance) * 100 * MICROUNIT
+}
+
 impl pallet_balances::Config for Runtime {
 	type MaxLocks = ConstU32<50>;
 	type MaxReserves = ();
@@ -278,6 +289,42 @@ impl pallet_sudo::Config for Runtime {
 /// Configure the pallet-template in pallets/template.
 impl pallet_template::Config for Runtime {
 	type RuntimeEvent = RuntimeEvent;
+	type OfferTtl = ConstU32<{ 2 * MINUTES }>;
+	type MaxExpiriesPerBlock = ConstU32<256>;
+	type MaxOffersPerWindow = ConstU32<20>;
+	type OfferRateWindow = ConstU32<HOURS>;
+	type FeelessEra = ConstU32<DAYS>;
+	type MaxFeelessPerEra = ConstU32<5>;
+	type FeelessEraCapacity = ConstU32<2_000>;
+	type FeelessPowDifficulty = ConstU32<20>;
+	type FeelessProofLifetime = ConstU32<{ 10 * MINUTES }>;
+	type MaxRoomMembers = ConstU32<64>;
+	type MaxRoomNameLen = ConstU32<64>;
+	type MaxRoomKeyLen = ConstU32<128>;
+	type MaxOfferLen = ConstU32<{ 8 * 1024 }>;
+	type MaxWelcomeMsgLen = ConstU32<300>;
+	type MaxAnswerLen = ConstU32<{ 8 * 1024 }>;
+	type MaxIceCandidatesLen = ConstU32<{ 4 * 1024 }>;
+	type MaxContactNameLen = ConstU32<1000>;
+	type MaxContactAddrLen = ConstU32<1000>;
+	type MaxContacts = ConstU32<10_000>;
+	type MaxContactBatch = ConstU32<100>;
+	type MaxContactGroups = ConstU32<32>;
+	type MaxContactGroupNameLen = ConstU32<128>;
+	type MaxContactNoteLen = ConstU32<512>;
+	type MaxNicknameLen = ConstU32<32>;
+	type Currency = Balances;
+	type DepositPerItem = ConstU128<{ deposit(1, 0) }>;
+	type DepositPerByte = ConstU128<{ deposit(0, 1) }>;
+	type MaxAddressHistory = ConstU32<8>;
+	type MaxOneTimePrekeys = ConstU32<100>;
+	type MinOneTimePrekeys = ConstU32<10>;
+	type MaxDevices = ConstU32<8>;
+	type MaxDeviceLabelLen = ConstU32<32>;
+	type NicknameValidator = pallet_template::nickname::LowercaseAscii<ConstU32<3>>;
+	type ReservedNicknameOrigin = frame_system::EnsureRoot<AccountId>;
+	// estimated weights until the pallet is benchmarked on reference hardware
+	type WeightInfo = ();
 }
 
 // Create the runtime by composing the FRAME pallets that were previously configured.
@@ -316,7 +363,11 @@ pub type SignedExtra = (
 	frame_system::CheckEra<Runtime>,
 	frame_system::CheckNonce<Runtime>,
 	frame_system::CheckWeight<Runtime>,
-	pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	pallet_template::ChargeOrFeeless<
+		Runtime,
+		pallet_transaction_payment::ChargeTransactionPayment<Runtime>,
+	>,
+	pallet_template::CheckOfferRateLimit<Runtime>,
 );
 
 /// Unchecked extrinsic type as expected by this runtime.
@@ -331,8 +382,24 @@ pub type Executive = frame_executive::Executive<
 	frame_system::ChainContext<Runtime>,
 	Runtime,
 	AllPalletsWithSystem,
+	Migrations,
 >;
 
+/// Storage migrations to run on the next runtime upgrade.
+type Migrations = (
+	pallet_template::migrations::v1::MigrateToV1<Runtime>,
+	pallet_template::migrations::v2::MigrateToV2<Runtime>,
+	pallet_template::migrations::v3::MigrateToV3<Runtime>,
+	pallet_template::migrations::v4::MigrateToV4<Runtime>,
+	pallet_template::migrations::v5::MigrateToV5<Runtime>,
+	pallet_template::migrations::v6::MigrateToV6<Runtime>,
+	pallet_template::migrations::v7::MigrateToV7<Runtime>,
+	pallet_template::migrations::v8::MigrateToV8<Runtime>,
+	pallet_template::migrations::v9::MigrateToV9<Runtime>,
+	pallet_template::migrations::v10::MigrateToV10<Runtime>,
+	pallet_template::migrations::v11::MigrateToV11<Runtime>,
+);
+
 #[cfg(feature = "runtime-benchmarks")]
 #[macro_use]
 extern crate frame_benchmarking;
@@ -496,6 +563,145 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_template_runtime_api::DiffyChatApi<Block, AccountId, BlockNumber> for Runtime {
+		fn resolve_nickname(nickname: Vec<u8>) -> Option<AccountId> {
+			TemplateModule::resolve_nickname(&nickname)
+		}
+
+		fn reverse_lookup(account: AccountId) -> Option<pallet_template_runtime_api::Registration> {
+			TemplateModule::reverse_lookup(&account).map(|item| {
+				pallet_template_runtime_api::Registration {
+					nickname: item.nickname.into_inner(),
+					address: item.address,
+				}
+			})
+		}
+
+		fn key_binding_payload(account: AccountId) -> Vec<u8> {
+			TemplateModule::key_binding_payload(&account)
+		}
+
+		fn devices_of(account: AccountId) -> Vec<pallet_template_runtime_api::Device<BlockNumber>> {
+			TemplateModule::get_devices(account)
+				.into_iter()
+				.map(|device| pallet_template_runtime_api::Device {
+					id: device.id,
+					public_key: device.public_key,
+					label: device.label.into_inner(),
+					added_at: device.added_at,
+				})
+				.collect()
+		}
+
+		fn contacts_of(
+			account: AccountId,
+			page: u32,
+		) -> Vec<pallet_template_runtime_api::Contact<AccountId>> {
+			TemplateModule::contacts_of(&account, page)
+				.into_iter()
+				.map(|(addr, contact)| pallet_template_runtime_api::Contact {
+					addr: addr.into_inner(),
+					name: contact.name.map(|name| name.into_inner()),
+					account: contact.account,
+					groups: contact.groups.into_inner(),
+					flags: contact.flags,
+					note: contact.note.map(|note| note.map(|note| note.into_inner())),
+				})
+				.collect()
+		}
+
+		fn contact_groups_of(account: AccountId) -> Vec<pallet_template_runtime_api::ContactGroup> {
+			TemplateModule::get_contact_groups(account)
+				.groups
+				.into_iter()
+				.map(|(id, name)| pallet_template_runtime_api::ContactGroup {
+					id,
+					name: name.map(|name| name.into_inner()),
+				})
+				.collect()
+		}
+
+		fn pending_offers(
+			account: AccountId,
+		) -> Vec<pallet_template_runtime_api::PendingOffer<AccountId, BlockNumber>> {
+			TemplateModule::pending_offers(&account)
+				.into_iter()
+				.map(|(offered_by, session_id, session)| {
+					pallet_template_runtime_api::PendingOffer {
+						offered_by,
+						session_id,
+						offer: session.offer.into_inner(),
+						welcome_msg: session.welcome_msg.into_inner(),
+						created_at: session.created_at,
+						room: session.room,
+						device: session.device,
+					}
+				})
+				.collect()
+		}
+
+		fn signals_of(account: AccountId) -> Vec<pallet_template_runtime_api::Signal<AccountId>> {
+			use pallet_template::Event;
+			use pallet_template_runtime_api::Signal;
+
+			System::read_events_no_consensus()
+				.into_iter()
+				.filter_map(|record| match record.event {
+					RuntimeEvent::TemplateModule(Event::Offer {
+						offer,
+						offered_by,
+						offered_to,
+						welcome_msg,
+						session_id,
+						room,
+						device,
+					}) if offered_to == account => Some(Signal::Offer {
+						offered_by,
+						session_id,
+						offer: offer.into_inner(),
+						welcome_msg: welcome_msg.into_inner(),
+						room,
+						device,
+					}),
+					RuntimeEvent::TemplateModule(Event::Answer {
+						answer,
+						answer_from,
+						answer_to,
+						session_id,
+					}) if answer_to == account => Some(Signal::Answer {
+						answer_from,
+						session_id,
+						answer: answer.into_inner(),
+					}),
+					RuntimeEvent::TemplateModule(Event::IceCandidates {
+						from,
+						to,
+						session_id,
+						candidates,
+					}) if to == account => Some(Signal::IceCandidates {
+						from,
+						session_id,
+						candidates: candidates.into_inner(),
+					}),
+					RuntimeEvent::TemplateModule(Event::OfferRejected {
+						offered_by,
+						offered_to,
+						session_id,
+						reason_code,
+					}) if offered_by == account => {
+						Some(Signal::Rejected { offered_to, session_id, reason_code })
+					},
+					RuntimeEvent::TemplateModule(Event::OfferCancelled {
+						offered_by,
+						offered_to,
+						session_id,
+					}) if offered_to == account => Some(Signal::Cancelled { offered_by, session_id }),
+					_ => None,
+				})
+				.collect()
+		}
+	}
+
 	#[cfg(feature = "runtime-benchmarks")]
 	impl frame_benchmarking::Benchmark<Block> for Runtime {
 		fn benchmark_metadata(extra: bool) -> (